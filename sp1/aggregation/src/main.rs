@@ -0,0 +1,110 @@
+//! This program aggregates several derivation proofs over consecutive chunks
+//! of a batch into a single derivation proof for the whole batch.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_primitives::B256;
+use espresso_derivation_utils::{
+    abi::{decode_public_values, encode_aggregated_public_values, PublicValuesStruct},
+    block::payload::{rollup_commit, Payload},
+    commitment::{RollupCommitmentScheme, SelectedScheme},
+    derivation::link_prev_batch,
+    PrevBatch,
+};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+pub fn main() {
+    // (public): digest of the verifying key of the derivation program
+    let vkey = sp1_zkvm::io::read::<[u32; 8]>();
+    // (private): `rollup_txs` is the list of all transactions in bytes form.
+    let rollup_txs = sp1_zkvm::io::read::<Payload>();
+    // (public): where the previous batch ended, if this proof is chained to it.
+    let prev_batch = sp1_zkvm::io::read::<Option<PrevBatch>>();
+    // (private): a pair of `(range, public_values)` for each chunk, where
+    //    `public_values` are ABI-encoded by a derivation proof for `range` of
+    //    `rollup_txs`.
+    let chunks = sp1_zkvm::io::read::<Vec<(Range<usize>, Vec<u8>)>>();
+    std::println!("All inputs are loaded");
+
+    // Verify each chunk proof and recover its public inputs
+    let chunk_inputs: Vec<PublicValuesStruct> = chunks
+        .iter()
+        .map(|(_, public_values)| {
+            let public_values_digest = Sha256::digest(public_values);
+            sp1_zkvm::lib::verify::verify_sp1_proof(&vkey, &public_values_digest.into());
+            decode_public_values(public_values).unwrap()
+        })
+        .collect();
+
+    // Check that the chunks are consistent and contiguous
+    // 1. The ranges cover the whole payload with no overlapping, and each chunk
+    //    commits to its own range with the same scheme as this program
+    // 2. All chunks share the same namespace, VID parameter and block Merkle
+    //    tree commitment
+    // 3. Block ranges of the chunks are back-to-back, and chunks aren't linked to
+    //    other batches themselves
+    let first = chunk_inputs.first().expect("At least one chunk is required.");
+    let mut end = 0;
+    let mut prev_height = None;
+    chunks
+        .iter()
+        .zip(chunk_inputs.iter())
+        .for_each(|((range, _), inputs)| {
+            assert_eq!(range.start, end);
+            assert_eq!(
+                inputs.rollupCommitScheme,
+                SelectedScheme::ID.to_u8(),
+                "Chunk uses another rollup commitment scheme."
+            );
+            assert_eq!(
+                inputs.rollupTxsCommit,
+                B256::from(
+                    rollup_commit(&Payload(rollup_txs.0[range.start..range.end].to_vec())).0
+                ),
+                "Chunk transactions are inconsistent with its proof."
+            );
+            assert_eq!(inputs.nsId, first.nsId);
+            assert_eq!(inputs.vidParamHash, first.vidParamHash);
+            assert_eq!(inputs.bmtDigest, first.bmtDigest);
+            assert_eq!(inputs.bmtHeight, first.bmtHeight);
+            assert_eq!(inputs.bmtNumLeaves, first.bmtNumLeaves);
+            assert!(!inputs.chained, "Chunks shouldn't be chained individually.");
+            if let Some(prev) = prev_height {
+                assert_eq!(prev + 1, inputs.startHeight, "Chunks are not contiguous.");
+            }
+            end = range.end;
+            prev_height = Some(inputs.endHeight);
+        });
+    assert_eq!(end, rollup_txs.0.len());
+
+    // Compute the commitment of all the transactions
+    let rollup_txs_commit = rollup_commit(&rollup_txs);
+    let start_height = first.startHeight;
+    let accumulator = link_prev_batch(prev_batch.as_ref(), start_height, &rollup_txs_commit)
+        .unwrap_or_else(|err| panic!("{err}"));
+
+    // Wrap all the public inputs
+    let public_inputs = PublicValuesStruct {
+        rollupCommitScheme: SelectedScheme::ID.to_u8(),
+        rollupTxsCommit: B256::from(rollup_txs_commit.0),
+        vidParamHash: first.vidParamHash,
+        nsId: first.nsId,
+        bmtDigest: first.bmtDigest,
+        bmtHeight: first.bmtHeight,
+        bmtNumLeaves: first.bmtNumLeaves,
+        startHeight: start_height,
+        endHeight: prev_height.unwrap(),
+        chained: prev_batch.is_some(),
+        prevEndHeight: prev_batch.map_or(0, |prev_batch| prev_batch.end_height),
+        prevAccumulator: prev_batch.map_or(B256::ZERO, |prev_batch| {
+            B256::from(prev_batch.accumulator.0)
+        }),
+        accumulator: B256::from(accumulator.0),
+    };
+
+    // Mark them as public inputs, binding the aggregated proofs to the
+    // derivation program.
+    sp1_zkvm::io::commit_slice(&encode_aggregated_public_values(&vkey, public_inputs));
+}
@@ -0,0 +1,68 @@
+//! This program composes a derivation proof, a VM execution proof and an
+//! Espresso light client proof into a single statement: the rollup executed
+//! exactly the transactions derived from Espresso blocks finalized in the
+//! light client state.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use espresso_derivation_utils::{
+    abi::{decode_light_client_public_values, encode_consistency_public_values},
+    consistency::{check_consistency, VmPublicInputs},
+    light_client::LightClientState,
+};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+/// Verify the SP1 proof of the program with `vkey` committing
+/// `public_values`, then decode them.
+fn verify_sub_proof<T: DeserializeOwned>(vkey: &[u32; 8], public_values: &[u8]) -> T {
+    verify_sp1_proof(vkey, public_values);
+    bincode::deserialize(public_values).unwrap()
+}
+
+/// Verify the SP1 proof of the program with `vkey` committing
+/// `public_values`.
+fn verify_sp1_proof(vkey: &[u32; 8], public_values: &[u8]) {
+    let public_values_digest = Sha256::digest(public_values);
+    sp1_zkvm::lib::verify::verify_sp1_proof(vkey, &public_values_digest.into());
+}
+
+pub fn main() {
+    // (public): digest of the verifying key of the derivation program
+    let derivation_vkey = sp1_zkvm::io::read::<[u32; 8]>();
+    // (private): public values of a derivation proof in light client mode
+    let derivation_public_values = sp1_zkvm::io::read::<Vec<u8>>();
+    // (public): digest of the verifying key of the VM execution program
+    let vm_vkey = sp1_zkvm::io::read::<[u32; 8]>();
+    // (private): public values of the VM execution proof
+    let vm_public_values = sp1_zkvm::io::read::<Vec<u8>>();
+    // (public): digest of the verifying key of the light client program
+    let light_client_vkey = sp1_zkvm::io::read::<[u32; 8]>();
+    // (private): public values of the light client proof
+    let light_client_public_values = sp1_zkvm::io::read::<Vec<u8>>();
+    std::println!("All inputs are loaded");
+
+    // Verify each sub-proof and recover its public inputs, the derivation proof
+    // being in light client mode
+    verify_sp1_proof(&derivation_vkey, &derivation_public_values);
+    let derivation = decode_light_client_public_values(&derivation_public_values)
+        .expect("Derivation proof is not in light client mode.");
+    let vm: VmPublicInputs = verify_sub_proof(&vm_vkey, &vm_public_values);
+    let light_client_state: LightClientState =
+        verify_sub_proof(&light_client_vkey, &light_client_public_values);
+
+    // Check that they are consistent and wrap all the public inputs
+    let public_inputs = check_consistency(
+        derivation_vkey,
+        vm_vkey,
+        light_client_vkey,
+        derivation,
+        vm,
+        light_client_state,
+    )
+    .unwrap_or_else(|err| panic!("{err}"));
+
+    // Mark them as public inputs, ABI-encoded
+    sp1_zkvm::io::commit_slice(&encode_consistency_public_values(&public_inputs));
+}
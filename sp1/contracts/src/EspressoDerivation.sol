@@ -3,6 +3,34 @@ pragma solidity ^0.8.20;
 
 import {ISP1Verifier} from "@sp1-contracts/ISP1Verifier.sol";
 
+/// @notice Public values of a derivation proof, see `PublicValuesStruct` in
+///         `espresso-derivation-utils`.
+struct PublicValuesStruct {
+    /// @dev 0 for SHA-256, 1 for Keccak-256, 2 for Poseidon over BN254, 3 for EIP-4844 blobs.
+    uint8 rollupCommitScheme;
+    bytes32 rollupTxsCommit;
+    bytes32 vidParamHash;
+    uint32 nsId;
+    bytes32 bmtDigest;
+    uint64 bmtHeight;
+    uint64 bmtNumLeaves;
+    uint64 startHeight;
+    uint64 endHeight;
+    bool chained;
+    uint64 prevEndHeight;
+    bytes32 prevAccumulator;
+    bytes32 accumulator;
+}
+
+/// @notice Public values of a derivation proof in non-inclusion mode, see
+///         `NonInclusionPublicValuesStruct` in `espresso-derivation-utils`.
+struct NonInclusionPublicValuesStruct {
+    PublicValuesStruct derivation;
+    bytes32 txHash;
+    /// @dev Whether no transaction derived in `derivation` has hash `txHash`.
+    bool notIncluded;
+}
+
 /// @title Fibonacci.
 /// @author Espresso System
 /// @notice This contract implements a simple example of verifying the proof of a computing a
@@ -18,6 +46,16 @@ contract EspressoDerivation {
     /// @notice The verification key for the program.
     bytes32 public vkey;
 
+    /// @notice Discriminator of the derivation mode committing `PublicValuesStruct`, the first
+    ///         word of the public values, see `DerivationMode::id` in `espresso-derivation-utils`.
+    uint8 public constant DERIVATION_MODE = 0;
+
+    /// @notice Discriminator of the non-inclusion mode, committing `NonInclusionPublicValuesStruct`.
+    uint8 public constant NON_INCLUSION_MODE = 1;
+
+    /// @notice The proof is of another mode of the derivation program.
+    error WrongDerivationMode(uint8 mode);
+
     constructor(address _verifier, bytes32 _vkey) {
         verifier = _verifier;
         vkey = _vkey;
@@ -25,11 +63,36 @@ contract EspressoDerivation {
 
     /// @notice Verify a derivation proof from a batch of Espresso blocks.
     /// @param proof The encoded proof.
-    /// @param publicValues The encoded public values.
+    /// @param publicValues The ABI-encoded mode and public values.
+    /// @return The decoded public values.
     function verifyDerivationProof(bytes calldata proof, bytes calldata publicValues)
         public
         view
+        returns (PublicValuesStruct memory)
+    {
+        ISP1Verifier(verifier).verifyProof(vkey, publicValues, proof);
+        (uint8 mode, PublicValuesStruct memory values) = abi.decode(publicValues, (uint8, PublicValuesStruct));
+        if (mode != DERIVATION_MODE) {
+            revert WrongDerivationMode(mode);
+        }
+        return values;
+    }
+
+    /// @notice Verify a proof that a transaction is absent from a batch of Espresso blocks.
+    /// @param proof The encoded proof.
+    /// @param publicValues The ABI-encoded mode and public values.
+    /// @return The decoded public values.
+    function verifyNonInclusionProof(bytes calldata proof, bytes calldata publicValues)
+        public
+        view
+        returns (NonInclusionPublicValuesStruct memory)
     {
         ISP1Verifier(verifier).verifyProof(vkey, publicValues, proof);
+        (uint8 mode, NonInclusionPublicValuesStruct memory values) =
+            abi.decode(publicValues, (uint8, NonInclusionPublicValuesStruct));
+        if (mode != NON_INCLUSION_MODE) {
+            revert WrongDerivationMode(mode);
+        }
+        return values;
     }
 }
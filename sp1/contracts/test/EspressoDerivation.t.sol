@@ -3,8 +3,13 @@ pragma solidity ^0.8.20;
 
 import {Test, console} from "forge-std/Test.sol";
 import {stdJson} from "forge-std/StdJson.sol";
-import {EspressoDerivation} from "../src/EspressoDerivation.sol";
+import {
+    EspressoDerivation,
+    NonInclusionPublicValuesStruct,
+    PublicValuesStruct
+} from "../src/EspressoDerivation.sol";
 import {SP1Verifier} from "@sp1-contracts/v1.1.0/SP1Verifier.sol";
+import {SP1MockVerifier} from "@sp1-contracts/SP1MockVerifier.sol";
 
 struct SP1ProofFixtureJson {
     bytes proof;
@@ -36,7 +41,8 @@ contract EspressoDerivationTest is Test {
     function test_ValidDerivationProof() public view {
         SP1ProofFixtureJson memory fixture = loadFixture();
 
-        es.verifyDerivationProof(fixture.proof, fixture.publicValues);
+        PublicValuesStruct memory publicValues = es.verifyDerivationProof(fixture.proof, fixture.publicValues);
+        assertLe(publicValues.startHeight, publicValues.endHeight);
     }
 
     function testFail_InvalidDerivationProof() public view {
@@ -48,3 +54,76 @@ contract EspressoDerivationTest is Test {
         es.verifyDerivationProof(fakeProof, fixture.publicValues);
     }
 }
+
+/// @notice Decoding of the public values, independent of the proof in the fixture: the mock
+///         verifier accepts any public values with an empty proof.
+contract EspressoDerivationDecodeTest is Test {
+    EspressoDerivation public es;
+
+    function setUp() public {
+        es = new EspressoDerivation(address(new SP1MockVerifier()), bytes32(0));
+    }
+
+    /// @dev Public values encoded by `espresso-derivation-utils`, see `test_public_values_vector`.
+    function loadPublicValues() public view returns (bytes memory) {
+        return loadHex("public_values.hex");
+    }
+
+    /// @dev Same derivation, in non-inclusion mode, see `test_non_inclusion_public_values_vector`.
+    function loadNonInclusionPublicValues() public view returns (bytes memory) {
+        return loadHex("non_inclusion_public_values.hex");
+    }
+
+    function loadHex(string memory name) internal view returns (bytes memory) {
+        string memory root = vm.projectRoot();
+        string memory path = string.concat(root, "/src/fixtures/", name);
+        return vm.parseBytes(vm.readFile(path));
+    }
+
+    function test_DecodePublicValues() public view {
+        PublicValuesStruct memory publicValues = es.verifyDerivationProof("", loadPublicValues());
+        assertEq(publicValues.rollupCommitScheme, 1);
+        // 0x0101...01
+        assertEq(publicValues.rollupTxsCommit, bytes32(type(uint256).max / 255));
+        assertEq(publicValues.nsId, 42);
+        assertEq(publicValues.bmtHeight, 32);
+        assertEq(publicValues.bmtNumLeaves, 13);
+        assertEq(publicValues.startHeight, 10);
+        assertEq(publicValues.endHeight, 12);
+        assertTrue(publicValues.chained);
+        assertEq(publicValues.prevEndHeight, 9);
+    }
+
+    function test_RevertWhen_OtherMode() public {
+        bytes memory publicValues = loadPublicValues();
+        // Light client mode
+        publicValues[31] = bytes1(uint8(3));
+
+        vm.expectRevert(abi.encodeWithSelector(EspressoDerivation.WrongDerivationMode.selector, uint8(3)));
+        es.verifyDerivationProof("", publicValues);
+    }
+
+    function test_DecodeNonInclusionPublicValues() public view {
+        NonInclusionPublicValuesStruct memory publicValues =
+            es.verifyNonInclusionProof("", loadNonInclusionPublicValues());
+        assertEq(publicValues.derivation.nsId, 42);
+        assertEq(publicValues.derivation.startHeight, 10);
+        assertEq(publicValues.derivation.endHeight, 12);
+        // 0x0606...06
+        assertEq(publicValues.txHash, bytes32(type(uint256).max / 255 * 6));
+        assertTrue(publicValues.notIncluded);
+    }
+
+    function test_RevertWhen_NonInclusionOfOtherMode() public {
+        bytes memory publicValues = loadNonInclusionPublicValues();
+        // Plain derivation mode
+        publicValues[31] = bytes1(uint8(0));
+
+        vm.expectRevert(abi.encodeWithSelector(EspressoDerivation.WrongDerivationMode.selector, uint8(0)));
+        es.verifyNonInclusionProof("", publicValues);
+
+        // The plain derivation can't be taken from a non-inclusion proof either
+        vm.expectRevert(abi.encodeWithSelector(EspressoDerivation.WrongDerivationMode.selector, uint8(1)));
+        es.verifyDerivationProof("", loadNonInclusionPublicValues());
+    }
+}
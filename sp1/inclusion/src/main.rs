@@ -0,0 +1,34 @@
+//! This program proves that a rollup transaction is included in an espresso
+//! block.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use espresso_derivation_utils::{
+    block::{header::BlockMerkleCommitment, payload::VidParam},
+    inclusion::{verify_tx_inclusion, TxInclusionProof},
+};
+
+pub fn main() {
+    // (private): (its hash is public) VID public parameter for checking the
+    // namespace proof
+    let vid_param = sp1_zkvm::io::read::<VidParam>();
+    // (public): namespace ID of this rollup
+    let ns_id = sp1_zkvm::io::read::<u32>();
+    // (public): `bmt_commitment`: the Espresso block Merkle tree commitment that
+    // the block is committed in.
+    let bmt_commitment = sp1_zkvm::io::read::<BlockMerkleCommitment>();
+    // (public): index of the transaction in the rollup's namespace
+    let tx_index = sp1_zkvm::io::read::<u32>();
+    // (private): the namespace bytes of the block and their derivation proof
+    let proof = sp1_zkvm::io::read::<TxInclusionProof>();
+    std::println!("All inputs are loaded");
+
+    // Verify the inclusion and wrap all the public inputs, including the block
+    // height and the transaction hash
+    let public_inputs = verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, tx_index, &proof)
+        .unwrap_or_else(|err| panic!("{err}"));
+
+    // Mark them as public inputs
+    sp1_zkvm::io::commit(&public_inputs);
+}
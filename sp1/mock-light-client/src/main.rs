@@ -0,0 +1,14 @@
+//! A mock Espresso light client program for testing the consistency program.
+//! It commits the given light client state without checking any consensus.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use espresso_derivation_utils::light_client::LightClientState;
+
+pub fn main() {
+    // (public): the "finalized" light client state
+    let state = sp1_zkvm::io::read::<LightClientState>();
+
+    sp1_zkvm::io::commit(&state);
+}
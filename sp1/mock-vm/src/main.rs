@@ -0,0 +1,34 @@
+//! A mock VM execution program for testing the consistency program. It
+//! "executes" the rollup transactions by hashing them into the state root.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use espresso_derivation_utils::{
+    block::payload::{rollup_commit, Payload},
+    commitment::{RollupCommitmentScheme, SelectedScheme},
+    consistency::VmPublicInputs,
+};
+use primitive_types::H256;
+use sha2::{Digest, Sha256};
+
+pub fn main() {
+    // (private): the executed transactions
+    let rollup_txs = sp1_zkvm::io::read::<Payload>();
+    // (public): state root before the execution
+    let prev_state_root = sp1_zkvm::io::read::<H256>();
+
+    let rollup_txs_commit = rollup_commit(&rollup_txs);
+    let new_state_root: [u8; 32] = Sha256::new()
+        .chain_update(prev_state_root)
+        .chain_update(rollup_txs_commit)
+        .finalize()
+        .into();
+
+    sp1_zkvm::io::commit(&VmPublicInputs {
+        rollup_commit_scheme: SelectedScheme::ID,
+        rollup_txs_commit,
+        prev_state_root,
+        new_state_root: new_state_root.into(),
+    });
+}
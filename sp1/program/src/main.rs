@@ -1,25 +1,41 @@
 //! This program proves that the executed transactions are correctly derived
 //! from espresso blocks.
+//!
+//! In non-inclusion mode, it further proves whether a given transaction is
+//! absent from all derived transactions. In blob mode, the transactions are
+//! committed with the versioned hash of the EIP-4844 blob holding them. In
+//! light client mode, the block Merkle tree commitment is bound to a public
+//! light client state. In finality mode, it is bound to a block header
+//! finalized by a quorum certificate of the Espresso stake table.
+//!
+//! In every mode, the discriminator of the mode is committed first, so that
+//! the public values of a mode can't be taken for those of another.
 
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use committable::Committable;
 use espresso_derivation_utils::{
+    abi::{
+        encode_blob_public_values, encode_finality_public_values,
+        encode_light_client_public_values, encode_non_inclusion_public_values,
+        encode_public_values,
+    },
+    blob::{verify_blob_derivation, BlobSetup},
     block::{
-        header::{BlockMerkleCommitment, BlockMerkleTree},
-        payload::{compute_vid_param_hash, rollup_commit, vid_scheme, Payload, Vid, VidParam},
+        header::BlockMerkleCommitment,
+        payload::{Payload, VidParam},
     },
-    BlockDerivationProof, PublicInputs,
-};
-use jf_merkle_tree::{MerkleCommitment, MerkleTreeScheme};
-use jf_vid::{
-    payload_prover::{PayloadProver, Statement},
-    VidScheme,
+    derivation::verify_derivation,
+    finality::{verify_finalized_derivation, FinalityProof},
+    inclusion::verify_non_inclusion,
+    light_client::verify_light_client_derivation,
+    BlockDerivationProof, DerivationMode, PrevBatch,
 };
 use std::ops::Range;
 
 pub fn main() {
+    // (public): what to prove, see `DerivationMode`
+    let mode = sp1_zkvm::io::read::<DerivationMode>();
     // (private): `rollup_txs` is the list of all transactions in bytes form.
     let rollup_txs = sp1_zkvm::io::read::<Payload>();
     // (private): (its hash is public) VID public parameter for checking the
@@ -28,101 +44,115 @@ pub fn main() {
     // (public): namespace ID of this rollup
     let ns_id = sp1_zkvm::io::read::<u32>();
     // (public): `bmt_commitment`: the Espresso block Merkle tree commitment that
-    // accumulates all block commitments up to the current `BlockHeight`.
+    // accumulates all block commitments up to the current `BlockHeight`. In
+    // light client mode, it is checked against the light client state instead.
     let bmt_commitment = sp1_zkvm::io::read::<BlockMerkleCommitment>();
+    // (public): where the previous batch ended, if this proof is chained to it.
+    let prev_batch = sp1_zkvm::io::read::<Option<PrevBatch>>();
     // (private): a pair of `(range, proof)` where the
     //    `proof` asserts that a `range` of `payload` is derived from some block
     //    committed in the block Merkle tree above.
     let block_derivation_proofs = sp1_zkvm::io::read::<Vec<(Range<usize>, BlockDerivationProof)>>();
     std::println!("All inputs are loaded");
 
-    // Compute the commitment of all the transactions
-    let rollup_txs_commit = rollup_commit(&rollup_txs);
+    match mode {
+        DerivationMode::Derivation => {
+            // Verify the Espresso derivation proof and wrap all the public inputs
+            let public_inputs = verify_derivation(
+                &rollup_txs,
+                &vid_param,
+                ns_id,
+                bmt_commitment,
+                prev_batch,
+                &block_derivation_proofs,
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
-    // Verify the Espresso derivation proof
-    // 1. Check that the ranges cover the whole payload with no overlapping
-    // 2. Check each block derivation proof
-    let mut end = 0;
-    block_derivation_proofs
-        .iter()
-        .for_each(|(range, block_proof)| {
-            assert_eq!(range.start, end);
-            verify_block_derivation_proof(
-                &rollup_txs.0[range.start..range.end],
+            // Mark them as public inputs after the mode, ABI-encoded for the L1
+            // contract
+            sp1_zkvm::io::commit_slice(&encode_public_values(&public_inputs));
+        },
+        DerivationMode::NonInclusion { tx_hash } => {
+            // Verify the Espresso derivation proof, then scan all the derived
+            // transactions for `tx_hash`
+            let public_inputs = verify_non_inclusion(
+                &rollup_txs,
                 &vid_param,
                 ns_id,
-                &bmt_commitment,
-                block_proof,
-            );
-            end = range.end;
-        });
-    assert_eq!(end, rollup_txs.0.len());
+                bmt_commitment,
+                prev_batch,
+                &block_derivation_proofs,
+                tx_hash,
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
-    // Wrap all the public inputs
-    let public_inputs = PublicInputs {
-        rollup_txs_commit,
-        vid_param_hash: compute_vid_param_hash(&vid_param),
-        ns_id,
-        bmt_commitment,
-    };
+            // Mark them as public inputs after the mode, ABI-encoded for the L1
+            // contract
+            sp1_zkvm::io::commit_slice(&encode_non_inclusion_public_values(&public_inputs));
+        },
+        DerivationMode::Blob => {
+            // (private): (its hash is public) setup of the Ethereum KZG ceremony
+            // for committing to the blob
+            let setup = sp1_zkvm::io::read::<BlobSetup>();
 
-    // Mark them as public inputs
-    sp1_zkvm::io::commit(&public_inputs);
-}
+            // Verify the Espresso derivation proof, with the blob versioned hash
+            // as the commitment of all the transactions
+            let public_inputs = verify_blob_derivation(
+                &rollup_txs,
+                &setup,
+                &vid_param,
+                ns_id,
+                bmt_commitment,
+                prev_batch,
+                &block_derivation_proofs,
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
-/// Verifies the block derivation proof against the public inputs
-fn verify_block_derivation_proof(
-    payload_slice: &[u8],
-    vid_param: &VidParam,
-    ns_id: u32,
-    bmt_commitment: &BlockMerkleCommitment,
-    proof: &BlockDerivationProof,
-) {
-    // Assert that the membership proof is valid
-    if bmt_commitment.height() + 1 != proof.bmt_proof.proof.len()
-        || !BlockMerkleTree::verify(
-            bmt_commitment.digest(),
-            proof.bmt_proof.pos,
-            &proof.bmt_proof,
-        )
-        .is_ok_and(|result| result.is_ok())
-    {
-        panic!("Incorrect membership proof for block Merkle tree.");
-    }
-    // Assert that the header is the one committed in the block Merkle tree
-    if !proof
-        .bmt_proof
-        .elem()
-        .is_some_and(|elem| elem == &proof.block_header.commit())
-    {
-        panic!("Membership proof is not consistent with the given block header.");
-    }
+            // Mark them as public inputs after the mode, ABI-encoded to be
+            // checked against the `BLOBHASH` of the blob
+            sp1_zkvm::io::commit_slice(&encode_blob_public_values(&public_inputs));
+        },
+        DerivationMode::LightClient { state } => {
+            // Verify that the block Merkle tree commitment is the one of the
+            // light client state, then the Espresso derivation proof
+            let public_inputs = verify_light_client_derivation(
+                state,
+                &rollup_txs,
+                &vid_param,
+                ns_id,
+                bmt_commitment,
+                prev_batch,
+                &block_derivation_proofs,
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
-    match proof.block_header.ns_table.scan_for_id(ns_id) {
-        None => {
-            panic!("Namespace ID not found in the block.");
+            // Mark them as public inputs after the mode, ABI-encoded to be
+            // checked against the state of the light client contract
+            sp1_zkvm::io::commit_slice(&encode_light_client_public_values(&public_inputs));
         },
-        Some((ns_range_start, ns_range_end)) => {
-            std::println!("Byte range: ({}, {})", ns_range_start, ns_range_end);
+        DerivationMode::Finality { stake_table_comm } => {
+            // (private): leaf of the finalized block, its quorum certificate
+            // and the stake table (committed in `stake_table_comm`) signing it
+            let finality_proof = sp1_zkvm::io::read::<FinalityProof>();
+
+            // Verify the quorum certificate on the leaf and that its header
+            // commits to the block Merkle tree, then the Espresso derivation
+            // proof
+            let public_inputs = verify_finalized_derivation(
+                stake_table_comm,
+                &finality_proof,
+                &rollup_txs,
+                &vid_param,
+                ns_id,
+                bmt_commitment,
+                prev_batch,
+                &block_derivation_proofs,
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
-            // Namespace proof w.r.t the VidCommitment
-            let num_storage_nodes =
-                <Vid as VidScheme>::get_num_storage_nodes(proof.vid_common.as_ref());
-            let vid = vid_scheme(num_storage_nodes, vid_param);
-            if !vid
-                .payload_verify(
-                    Statement {
-                        payload_subslice: payload_slice,
-                        range: (ns_range_start as usize..ns_range_end as usize),
-                        commit: proof.block_header.payload_commitment.as_ref(),
-                        common: proof.vid_common.as_ref(),
-                    },
-                    proof.ns_proof.as_ref(),
-                )
-                .is_ok_and(|result| result.is_ok())
-            {
-                panic!("Failed namespace proof.");
-            }
+            // Mark them as public inputs after the mode, ABI-encoded to be
+            // checked against the stake table of the light client contract
+            sp1_zkvm::io::commit_slice(&encode_finality_public_values(&public_inputs));
         },
     }
 }
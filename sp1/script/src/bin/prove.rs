@@ -6,39 +6,51 @@
 //! RUST_LOG=info cargo run --package espresso-derivation-prover --bin prove --release
 //! ```
 
-use clap::Parser;
-use committable::Committable;
+use clap::{Parser, Subcommand};
+use espresso_derivation_prover::{
+    client::QueryServiceClient,
+    inputs::{load_and_validate_inputs, load_inputs, save_inputs, write_inputs},
+    mock::mock_inputs,
+    srs::SrsArgs,
+};
 use espresso_derivation_utils::{
-    block::{
-        header::{BlockHeader, BlockMerkleTree},
-        payload::{vid_scheme, NsProof, Payload, Vid, VidCommitment, VidCommon, VidParam},
+    abi::{
+        decode_aggregated_public_values, decode_blob_public_values, decode_finality_public_values,
+        decode_light_client_public_values, decode_mode, decode_non_inclusion_public_values,
+        decode_public_values,
     },
-    ns_table::NsTable,
-    BlockDerivationProof, PublicInputs,
+    blob::BlobSetup,
+    block::payload::{compute_vid_param_hash, Payload, VidParam},
+    finality::FinalityProof,
+    inclusion::{verify_tx_inclusion, InclusionPublicInputs},
+    light_client::LightClientState,
+    DerivationInputs, DerivationMode, PrevBatch,
 };
-use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleTreeScheme};
-use jf_pcs::prelude::UnivariateUniversalParams;
-use jf_vid::{payload_prover::PayloadProver, VidScheme};
-use rand::{Rng, RngCore, SeedableRng};
+use primitive_types::H256;
 use serde::{Deserialize, Serialize};
-use sp1_sdk::{HashableKey, ProverClient, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
-use std::path::PathBuf;
+use sp1_sdk::{
+    HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues, SP1ProvingKey, SP1Stdin,
+    SP1VerifyingKey,
+};
+use std::{
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 /// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
 ///
 /// This file is generated by running `cargo prove build` inside the `program`
 /// directory.
 pub const ELF: &[u8] = include_bytes!("../../../program/elf/riscv32im-succinct-zkvm-elf");
-/// low degree for demo only
-pub const SRS_DEGREE: usize = 8usize;
-/// payload bytes for each block shouldn't exceed max size
-/// during encoding, every 30 bytes is converted to a 254-bit field element
-pub const MAX_PAYLOAD_BYTES_PER_BLOCK: usize = SRS_DEGREE * 30;
-/// number of storage node for VID
-pub const NUM_STORAGE_NODES: u32 = 10;
-/// produce derivation proof for a batch of espresso blocks
-pub const NUM_BLOCKS: u64 = 5;
-
+/// The ELF file of the aggregation program, generated by running `cargo prove
+/// build` inside the `aggregation` directory.
+pub const AGGREGATION_ELF: &[u8] =
+    include_bytes!("../../../aggregation/elf/riscv32im-succinct-zkvm-elf");
+/// The ELF file of the transaction inclusion program, generated by running
+/// `cargo prove build` inside the `inclusion` directory.
+pub const INCLUSION_ELF: &[u8] =
+    include_bytes!("../../../inclusion/elf/riscv32im-succinct-zkvm-elf");
 /// The arguments for the prove command.
 // TODO: fill in other details
 #[derive(Parser, Debug)]
@@ -49,142 +61,145 @@ struct ProveArgs {
 
     #[clap(long, default_value = "false")]
     evm: bool,
-}
 
-fn mock_block<R: RngCore>(
-    idx: u64,
-    ns_id: u32,
-    ns_payload: &[u8],
-    vid: &mut Vid,
-    rng: &mut R,
-) -> (BlockHeader, VidCommon, NsProof) {
-    // This is a tweak from an actual block header in Espresso's staging testnet
-    let mut header: BlockHeader = serde_json::from_str(
-        r#"{
-            "chain_config": {
-                "chain_config": {
-                    "Left": {
-                        "chain_id": "888888888",
-                        "max_block_size": "30000000",
-                        "base_fee": "0",
-                        "fee_contract": null,
-                        "fee_recipient": "0x0000000000000000000000000000000000000000"
-                    }
-                }
-            },
-            "height": 69781,
-            "timestamp": 1720789795,
-            "l1_head": 5113,
-            "l1_finalized": {
-                "number": 5088,
-                "timestamp": "0x669129ec",
-                "hash": "0xfc4249b13292d2617cc0dec8b0a9a666491d5fecdfe536c929207847364b2b60"
-            },
-            "payload_commitment": "HASH~KpvHX4MuDuZKk10QJctEoUj-fump6NIAO8fJ048RwNJo",
-            "builder_commitment": "BUILDER_COMMITMENT~tEvs0rxqOiMCvfe2R0omNNaphSlUiEDrb2q0IZpRcgA_",
-            "ns_table": {
-                "bytes": "AQAAAB0AAAALAAAA"
-            },
-            "block_merkle_tree_root": "MERKLE_COMM~02gWBSt2tcz9XfOOO6xEVicluWIIP95BW8I11f2graggAAAAAAAAAJUQAQAAAAAAUQ",
-            "fee_merkle_tree_root": "MERKLE_COMM~yB4_Aqa35_PoskgTpcCR1oVLh6BUdLHIs7erHKWi-usUAAAAAAAAAAEAAAAAAAAAJg",
-            "fee_info": {
-                "account": "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f",
-                "amount": "0"
-            },
-            "builder_signature": {
-                "r": "0x6291b473fdac85b9ce7b40b530ea4173ac6e71fd29acffc3cbc97ae637d4404d",
-                "s": "0x3178fe07d5071df7a7ce4106e6e1e3727aa6edc458db03d1774948bdec32eac6",
-                "v": 28
-            }
-         }"#,
-    ).unwrap();
-
-    // Mock a height
-    header.height += idx;
-
-    // Mock payload
-    let payload_size = rng.gen_range(2 * ns_payload.len()..8 * ns_payload.len());
-    let mut payload = vec![0u8; payload_size];
-    rng.fill_bytes(&mut payload);
-    let offset = rng.gen_range(1..payload_size - ns_payload.len() - 1);
-    payload[offset..offset + ns_payload.len()].copy_from_slice(ns_payload);
-
-    // Mock VID information
-    let vid_disperse = vid.disperse(&payload).unwrap();
-    let vid_common = VidCommon(vid_disperse.common);
-    let vid_commitment = VidCommitment(vid_disperse.commit);
-    // Update the payload commitment
-    header.payload_commitment = vid_commitment;
-    // Update the namespace table
-    header.ns_table = NsTable::mock_ns_table(&[
-        (rng.next_u32(), offset as u32),
-        (ns_id, offset as u32 + ns_payload.len() as u32),
-        (rng.next_u32(), payload_size as u32),
-    ]);
-
-    // Namespace proof
-    let ns_range = offset..offset + ns_payload.len();
-    let ns_proof = NsProof(vid.payload_proof(&payload, ns_range).unwrap());
-
-    (header, vid_common, ns_proof)
+    /// Prove the batch in chunks of this many blocks, then aggregate the chunk
+    /// proofs into a single one.
+    #[clap(long)]
+    chunk_size: Option<NonZeroUsize>,
+
+    /// Load the inputs from this directory or file instead of mocking them.
+    /// See `espresso_derivation_prover::inputs` for the expected layout.
+    #[clap(long)]
+    inputs: Option<PathBuf>,
+
+    /// Save the inputs to this file (`.json` or `.bin`) before proving.
+    #[clap(long)]
+    save_inputs: Option<PathBuf>,
+
+    /// Prove whether the transaction with this hash is absent from the batch,
+    /// on top of its derivation.
+    #[clap(long, conflicts_with = "chunk_size")]
+    non_inclusion: Option<H256>,
+
+    /// Commit to the transactions with the versioned hash of their EIP-4844
+    /// blob, using the KZG setup in this `trusted_setup.txt` file of
+    /// `c-kzg-4844`.
+    #[clap(long, conflicts_with_all = ["chunk_size", "non_inclusion"])]
+    blob_setup: Option<PathBuf>,
+
+    /// Bind the block Merkle tree commitment to the light client state of
+    /// this view, as finalized by the light client contract.
+    #[clap(
+        long,
+        requires_all = ["light_client_height", "light_client_root"],
+        conflicts_with_all = ["chunk_size", "non_inclusion", "blob_setup"]
+    )]
+    light_client_view: Option<u64>,
+
+    /// Block height of the light client state.
+    #[clap(long, requires = "light_client_view")]
+    light_client_height: Option<u64>,
+
+    /// `blockCommRoot` of the light client state, the decimal `uint256`
+    /// stored by the contract.
+    #[clap(long, requires = "light_client_view")]
+    light_client_root: Option<String>,
+
+    /// Bind the block Merkle tree commitment to the header of a leaf finalized
+    /// by a quorum certificate, loaded with its stake table from this JSON
+    /// `FinalityProof` file.
+    #[clap(long, conflicts_with_all = ["chunk_size", "non_inclusion", "blob_setup", "light_client_view"])]
+    finality_proof: Option<PathBuf>,
+
+    #[clap(flatten)]
+    srs: SrsArgs,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-fn mock_inputs(stdin: &mut SP1Stdin) {
-    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
-
-    let ns_id = rng.next_u32();
-    let mut block_merkle_tree = BlockMerkleTree::new(32);
-    let mut rollup_payload = Payload(vec![]);
-    let mut block_proofs = vec![];
-
-    let vid_param = load_srs();
-    let mut vid = vid_scheme(NUM_STORAGE_NODES, &vid_param);
-
-    for i in 0..NUM_BLOCKS {
-        // pick a payload length for each block
-        let ns_payload_len = rng.gen_range(1..MAX_PAYLOAD_BYTES_PER_BLOCK);
-        // fill with random payload bytes of `ns_payload_len`
-        let mut block_ns_payload = vec![0u8; ns_payload_len];
-        rng.fill_bytes(&mut block_ns_payload);
-
-        // produce a mock block containing this namespace payload
-        let (header, vid_common, ns_proof) =
-            mock_block(i, ns_id, &block_ns_payload, &mut vid, &mut rng);
-
-        // push the block commitment to the BMT
-        block_merkle_tree.push(header.commit()).unwrap();
-
-        // retrieve a merkle proof of this block commitment
-        let (_, bmt_proof) = block_merkle_tree.lookup(i).expect_ok().unwrap();
-
-        // prepare the block derivation proof
-        block_proofs.push((
-            rollup_payload.0.len()..rollup_payload.0.len() + ns_payload_len,
-            BlockDerivationProof {
-                bmt_proof,
-                block_header: header,
-                vid_common,
-                ns_proof,
-            },
-        ));
-        // append to overall rollup-specific payload
-        // (as if filtered from a batch of blocks)
-        rollup_payload.0.append(&mut block_ns_payload);
-    }
-
-    // update all the BMT inclusion proof since new block commitments where
-    // accumulated, and old merkle proofs are holding outdated root
-    for i in 0..NUM_BLOCKS {
-        let (_, bmt_proof) = block_merkle_tree.lookup(i).expect_ok().unwrap();
-        block_proofs.get_mut(i as usize).unwrap().1.bmt_proof = bmt_proof;
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Natively verify a saved input set without proving, reporting which
+    /// block fails (if any).
+    VerifyInputs {
+        /// Path to the directory or file of the inputs
+        path: PathBuf,
+    },
+    /// Fetch the inputs for a range of blocks from an Espresso query service,
+    /// and save them to a file (`.json` or `.bin`).
+    FetchInputs {
+        /// URL of the query service API, e.g. `http://localhost:24000/v0`
+        #[clap(long)]
+        url: String,
+        /// Namespace ID of the rollup
+        #[clap(long)]
+        ns_id: u32,
+        /// Height of the first block to derive from
+        #[clap(long)]
+        start_height: u64,
+        /// Height of the last block to derive from (inclusive)
+        #[clap(long)]
+        end_height: u64,
+        /// Height of the header whose block Merkle tree commitment is used,
+        /// must be greater than `end_height`
+        #[clap(long)]
+        bmt_height: u64,
+        /// Path to save the inputs
+        output: PathBuf,
+    },
+    /// Prove that a rollup transaction is included in an Espresso block,
+    /// fetching the block from a query service.
+    ProveInclusion {
+        /// URL of the query service API, e.g. `http://localhost:24000/v0`
+        #[clap(long)]
+        url: String,
+        /// Namespace ID of the rollup
+        #[clap(long)]
+        ns_id: u32,
+        /// Height of the block including the transaction
+        #[clap(long)]
+        height: u64,
+        /// Index of the transaction in the rollup's namespace of the block
+        #[clap(long)]
+        tx_index: u32,
+        /// Height of the header whose block Merkle tree commitment is used,
+        /// must be greater than `height`
+        #[clap(long)]
+        bmt_height: u64,
+    },
+    /// Load the SRS of the selected profile, and print its degree and
+    /// `vid_param_hash`.
+    SrsInfo,
+}
 
-    // push to inputs
-    stdin.write(&rollup_payload);
+/// Write the inputs for a chunk of `blocks` only, as an unchained batch.
+/// Return the range of `rollup_txs` covered by this chunk.
+fn write_chunk_inputs(
+    inputs: &DerivationInputs,
+    blocks: Range<usize>,
+    stdin: &mut SP1Stdin,
+) -> Range<usize> {
+    let block_proofs = &inputs.block_derivation_proofs[blocks];
+    let vid_param = inputs
+        .vid_param
+        .truncate_for(block_proofs.iter().map(|(_, proof)| &proof.vid_common));
+    let start = block_proofs.first().unwrap().0.start;
+    let end = block_proofs.last().unwrap().0.end;
+    // Ranges are relative to the chunk's own transactions
+    let block_proofs: Vec<_> = block_proofs
+        .iter()
+        .map(|(range, proof)| (range.start - start..range.end - start, proof))
+        .collect();
+
+    stdin.write(&DerivationMode::Derivation);
+    stdin.write(&Payload(inputs.rollup_txs.0[start..end].to_vec()));
     stdin.write(&vid_param);
-    stdin.write(&ns_id);
-    stdin.write(&block_merkle_tree.commitment());
+    stdin.write(&inputs.ns_id);
+    stdin.write(&inputs.bmt_commitment);
+    stdin.write(&None::<PrevBatch>);
     stdin.write(&block_proofs);
+    start..end
 }
 
 fn main() {
@@ -194,15 +209,117 @@ fn main() {
     // Parse the command line arguments.
     let args = ProveArgs::parse();
 
+    match &args.command {
+        Some(Command::VerifyInputs { path }) => {
+            verify_inputs(path);
+            return;
+        },
+        Some(Command::FetchInputs {
+            url,
+            ns_id,
+            start_height,
+            end_height,
+            bmt_height,
+            output,
+        }) => {
+            let inputs = QueryServiceClient::new(url)
+                .derivation_batch(*start_height..=*end_height, *ns_id, *bmt_height)
+                .expect("failed to fetch inputs")
+                .into_inputs(load_srs(&args.srs), *ns_id, None);
+            save_inputs(&inputs, output).expect("failed to save inputs");
+            println!("Inputs saved to {}", output.display());
+            return;
+        },
+        Some(Command::ProveInclusion {
+            url,
+            ns_id,
+            height,
+            tx_index,
+            bmt_height,
+        }) => {
+            prove_inclusion(
+                url,
+                *ns_id,
+                *height,
+                *tx_index,
+                *bmt_height,
+                load_srs(&args.srs),
+            );
+            return;
+        },
+        Some(Command::SrsInfo) => {
+            let vid_param = load_srs(&args.srs);
+            let profile = args.srs.srs_profile;
+            println!("Profile: {profile}, degree {}", profile.degree());
+            println!("vid_param_hash: {:?}", compute_vid_param_hash(&vid_param));
+            match profile.vid_param_hash() {
+                Some(pinned) => println!("Pinned vid_param_hash: {pinned:?}"),
+                None => println!("No pinned vid_param_hash for this profile"),
+            }
+            return;
+        },
+        None => {},
+    }
+
     // Setup the prover client.
     let client = ProverClient::new();
 
     // Setup the program.
     let (pk, vk) = client.setup(ELF);
 
-    // Setup the inputs.;
+    // Setup the inputs.
+    let inputs = match &args.inputs {
+        Some(path) => load_and_validate_inputs(path).expect("failed to load inputs"),
+        None => mock_inputs(load_srs(&args.srs)),
+    };
+    if let Some(path) = &args.save_inputs {
+        save_inputs(&inputs, path).expect("failed to save inputs");
+        println!("Inputs saved to {}", path.display());
+    }
+    let blob_setup = args.blob_setup.as_ref().map(|path| {
+        let txt = std::fs::read_to_string(path).expect("failed to read blob setup");
+        BlobSetup::from_trusted_setup_txt(&txt).expect("failed to parse blob setup")
+    });
+    let finality_proof = args.finality_proof.as_ref().map(|path| {
+        let json = std::fs::read_to_string(path).expect("failed to read finality proof");
+        serde_json::from_str::<FinalityProof>(&json).expect("failed to parse finality proof")
+    });
+    let mode = match (
+        args.non_inclusion,
+        &blob_setup,
+        args.light_client_view,
+        &finality_proof,
+    ) {
+        (Some(tx_hash), _, _, _) => DerivationMode::NonInclusion { tx_hash },
+        (None, Some(_), _, _) => DerivationMode::Blob,
+        (None, None, Some(view_num), _) => {
+            let state = LightClientState::from_contract(
+                view_num,
+                args.light_client_height.unwrap(),
+                args.light_client_root.as_deref().unwrap(),
+            )
+            .expect("invalid light client block commitment root");
+            // Fail early instead of after hours of proving
+            state
+                .check_bmt_commitment(&inputs.bmt_commitment)
+                .unwrap_or_else(|err| panic!("{err}"));
+            DerivationMode::LightClient { state }
+        },
+        (None, None, None, Some(finality_proof)) => DerivationMode::Finality {
+            stake_table_comm: finality_proof
+                .stake_table_comm()
+                .expect("stake table exceeds the light client capacity"),
+        },
+        (None, None, None, None) => DerivationMode::Derivation,
+    };
     let mut stdin = SP1Stdin::new();
-    mock_inputs(&mut stdin);
+    write_inputs(&inputs, mode, &mut stdin);
+    if let Some(setup) = &blob_setup {
+        stdin.write(setup);
+    }
+    if let Some(finality_proof) = &finality_proof {
+        stdin.write(finality_proof);
+    }
 
     if args.bench {
         // Execute the program
@@ -210,9 +327,10 @@ fn main() {
             .execute(ELF, stdin)
             .run()
             .expect("failed to generate proof");
-        let public_values: PublicInputs = bincode::deserialize(public_values.as_slice()).unwrap();
-        println!("Public values: {:?}", public_values);
+        print_public_values(public_values.as_slice(), mode);
         println!("{}", report);
+    } else if let Some(chunk_size) = args.chunk_size {
+        prove_aggregated(&client, &pk, &vk, &inputs, chunk_size, args.evm);
     } else if args.evm {
         // Generate the proof.
         let proof = client
@@ -227,15 +345,157 @@ fn main() {
             .prove(&pk, stdin)
             .run()
             .expect("failed to generate proof");
-        let public_values: PublicInputs =
-            bincode::deserialize(proof.public_values.as_slice()).unwrap();
-        println!("Public values: {:?}", public_values);
+        print_public_values(proof.public_values.as_slice(), mode);
 
         // Verify the proof.
         client.verify(&proof, &vk).expect("failed to verify proof");
     }
 }
 
+/// Decode and print the public values committed by the derivation program in
+/// `mode`.
+fn print_public_values(public_values: &[u8], mode: DerivationMode) {
+    let (mode_id, _) = decode_mode(public_values).unwrap();
+    assert_eq!(mode_id, mode.id(), "public values of another mode");
+    match mode {
+        DerivationMode::Derivation => {
+            let public_values = decode_public_values(public_values).unwrap();
+            println!("Public values: {:?}", public_values);
+        },
+        DerivationMode::NonInclusion { .. } => {
+            let public_values = decode_non_inclusion_public_values(public_values).unwrap();
+            println!("Public values: {:?}", public_values);
+        },
+        DerivationMode::Blob => {
+            let public_values = decode_blob_public_values(public_values).unwrap();
+            println!("Public values: {:?}", public_values);
+        },
+        DerivationMode::LightClient { .. } => {
+            let public_values = decode_light_client_public_values(public_values).unwrap();
+            println!("Public values: {:?}", public_values);
+        },
+        DerivationMode::Finality { .. } => {
+            let public_values = decode_finality_public_values(public_values).unwrap();
+            println!("Public values: {:?}", public_values);
+        },
+    }
+}
+
+/// Split the batch into chunks of `chunk_size` blocks, prove each chunk with
+/// the derivation program, then aggregate them into a single proof.
+fn prove_aggregated(
+    client: &ProverClient,
+    pk: &SP1ProvingKey,
+    vk: &SP1VerifyingKey,
+    inputs: &DerivationInputs,
+    chunk_size: NonZeroUsize,
+    evm: bool,
+) {
+    let (aggregation_pk, aggregation_vk) = client.setup(AGGREGATION_ELF);
+
+    // Generate compressed proofs for each chunk, so that they can be verified
+    // inside the aggregation program.
+    let num_blocks = inputs.block_derivation_proofs.len();
+    let chunk_proofs: Vec<_> = (0..num_blocks)
+        .step_by(chunk_size.get())
+        .map(|start| {
+            let blocks = start..std::cmp::min(start + chunk_size.get(), num_blocks);
+            tracing::info!("Proving blocks {:?} of the batch", blocks);
+            let mut stdin = SP1Stdin::new();
+            let range = write_chunk_inputs(inputs, blocks, &mut stdin);
+            let proof = client
+                .prove(pk, stdin)
+                .compressed()
+                .run()
+                .expect("failed to generate chunk proof");
+            (range, proof)
+        })
+        .collect();
+
+    // Setup the inputs for the aggregation program.
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&vk.hash_u32());
+    stdin.write(&inputs.rollup_txs);
+    stdin.write(&inputs.prev_batch);
+    stdin.write(
+        &chunk_proofs
+            .iter()
+            .map(|(range, proof)| (range.clone(), proof.public_values.to_vec()))
+            .collect::<Vec<_>>(),
+    );
+    for (_, proof) in chunk_proofs {
+        let SP1Proof::Compressed(proof) = proof.proof else {
+            panic!("chunk proof is not compressed");
+        };
+        stdin.write_proof(proof, vk.vk.clone());
+    }
+
+    if evm {
+        // Generate the proof.
+        let proof = client
+            .prove(&aggregation_pk, stdin)
+            .plonk()
+            .run()
+            .expect("failed to generate aggregation proof");
+        create_plonk_fixture(&proof, &aggregation_vk);
+    } else {
+        // Generate the proof.
+        let proof = client
+            .prove(&aggregation_pk, stdin)
+            .run()
+            .expect("failed to generate aggregation proof");
+        let public_values =
+            decode_aggregated_public_values(proof.public_values.as_slice()).unwrap();
+        println!("Public values: {:?}", public_values.derivation);
+
+        // Verify the proof.
+        client
+            .verify(&proof, &aggregation_vk)
+            .expect("failed to verify aggregation proof");
+    }
+}
+
+/// Prove that the transaction at `tx_index` in namespace `ns_id` of the block
+/// at `height` is included in the block Merkle tree committed at `bmt_height`.
+fn prove_inclusion(
+    url: &str,
+    ns_id: u32,
+    height: u64,
+    tx_index: u32,
+    bmt_height: u64,
+    vid_param: VidParam,
+) {
+    let (bmt_commitment, proof) = QueryServiceClient::new(url)
+        .tx_inclusion_proof(height, ns_id, bmt_height)
+        .expect("failed to fetch inclusion proof");
+    // Check natively before proving
+    if let Err(err) = verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, tx_index, &proof) {
+        eprintln!("Verification failed: {err}");
+        std::process::exit(1);
+    }
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&vid_param.truncate_for([&proof.block_proof.vid_common]));
+    stdin.write(&ns_id);
+    stdin.write(&bmt_commitment);
+    stdin.write(&tx_index);
+    stdin.write(&proof);
+
+    let client = ProverClient::new();
+    let (pk, vk) = client.setup(INCLUSION_ELF);
+    let proof = client
+        .prove(&pk, stdin)
+        .run()
+        .expect("failed to generate inclusion proof");
+    let public_values: InclusionPublicInputs =
+        bincode::deserialize(proof.public_values.as_slice()).unwrap();
+    println!("Public values: {:?}", public_values);
+
+    client
+        .verify(&proof, &vk)
+        .expect("failed to verify inclusion proof");
+}
+
 /// A fixture that can be used to test the verification of SP1 zkVM proofs
 /// inside Solidity.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -282,12 +542,25 @@ fn create_plonk_fixture(proof: &SP1ProofWithPublicValues, vk: &SP1VerifyingKey)
     .expect("failed to write fixture");
 }
 
-fn load_srs() -> VidParam {
-    let srs = ark_srs::kzg10::aztec20::setup(SRS_DEGREE).expect("Aztec SRS failed to load");
-    VidParam(UnivariateUniversalParams {
-        powers_of_g: srs.powers_of_g,
-        h: srs.h,
-        beta_h: srs.beta_h,
-        powers_of_h: vec![srs.h, srs.beta_h],
-    })
+/// Natively verify a saved input set.
+fn verify_inputs(path: &Path) {
+    let inputs = load_inputs(path).expect("failed to load inputs");
+    match inputs.verify() {
+        Ok(public_values) => {
+            println!(
+                "All {} blocks verified",
+                inputs.block_derivation_proofs.len()
+            );
+            println!("Public values: {:?}", public_values);
+        },
+        Err(err) => {
+            eprintln!("Verification failed: {err}");
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Load the SRS of the selected profile, see `espresso_derivation_prover::srs`.
+fn load_srs(args: &SrsArgs) -> VidParam {
+    args.load().expect("failed to load the SRS")
 }
@@ -0,0 +1,381 @@
+//! Client of an Espresso query service, assembling derivation inputs for a
+//! range of blocks.
+//!
+//! Endpoints are relative to the versioned API root of the query service, e.g.
+//! `https://query.main.net.espresso.network/v0`.
+
+use anyhow::{ensure, Context};
+use committable::{Commitment, Committable};
+use espresso_derivation_utils::{
+    block::{
+        header::{BlockHeader, BlockMerkleCommitment, BlockMerkleTreeProof},
+        payload::{NsProof, Payload, VidCommitment, VidCommon, VidParam},
+    },
+    inclusion::TxInclusionProof,
+    BlockDerivationProof, DerivationInputs, PrevBatch,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::ops::{Range, RangeInclusive};
+
+/// Response of `availability/payload/:height`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PayloadQueryData {
+    pub height: u64,
+    /// Commitment of the block header
+    pub block_hash: Commitment<BlockHeader>,
+    /// Payload commitment of the block
+    pub hash: VidCommitment,
+    /// Byte length of the payload
+    pub size: u64,
+    pub data: Payload,
+}
+
+/// Response of `availability/vid/common/:height`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VidCommonQueryData {
+    pub height: u64,
+    /// Commitment of the block header
+    pub block_hash: Commitment<BlockHeader>,
+    /// Payload commitment `common` belongs to
+    pub payload_hash: VidCommitment,
+    pub common: VidCommon,
+}
+
+/// Response of `availability/block/:height/namespace/:namespace`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespaceProofQueryData {
+    /// `None` if the namespace is absent from the block
+    pub proof: Option<NamespaceProof>,
+    /// Transactions of the namespace, empty if absent from the block
+    pub transactions: Vec<Transaction>,
+}
+
+/// A transaction as served by the query service.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub namespace: u32,
+    #[serde(with = "base64_bytes")]
+    pub payload: Vec<u8>,
+}
+
+/// Bytes of a namespace in a block, together with its namespace proof.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespaceProof {
+    /// The namespace's range of the block payload
+    #[serde(with = "base64_bytes")]
+    pub ns_payload: Vec<u8>,
+    /// Proof that `ns_payload` is the complete namespace range of the block
+    /// payload
+    pub ns_proof: NsProof,
+}
+
+/// Derivation proofs fetched for a range of blocks.
+#[derive(Debug)]
+pub struct DerivationBatch {
+    /// Concatenated namespace bytes of all blocks
+    pub rollup_txs: Payload,
+    /// The block Merkle tree commitment that all proofs are against
+    pub bmt_commitment: BlockMerkleCommitment,
+    /// A pair of `(range, proof)` for each block
+    pub block_derivation_proofs: Vec<(Range<usize>, BlockDerivationProof)>,
+}
+
+impl DerivationBatch {
+    /// Complete the inputs of the derivation program.
+    pub fn into_inputs(
+        self,
+        vid_param: VidParam,
+        ns_id: u32,
+        prev_batch: Option<PrevBatch>,
+    ) -> DerivationInputs {
+        DerivationInputs {
+            rollup_txs: self.rollup_txs,
+            vid_param,
+            ns_id,
+            bmt_commitment: self.bmt_commitment,
+            prev_batch,
+            block_derivation_proofs: self.block_derivation_proofs,
+        }
+    }
+}
+
+/// A blocking client of an Espresso query service.
+#[derive(Clone, Debug)]
+pub struct QueryServiceClient {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl QueryServiceClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let url = format!("{}/{path}", self.base_url);
+        self.agent
+            .get(&url)
+            .call()
+            .with_context(|| format!("failed to query {url}"))?
+            .into_json()
+            .with_context(|| format!("failed to decode response of {url}"))
+    }
+
+    /// Fetch the header of the block at `height`.
+    pub fn header(&self, height: u64) -> anyhow::Result<BlockHeader> {
+        self.get(&format!("availability/header/{height}"))
+    }
+
+    /// Fetch the full payload of the block at `height`.
+    pub fn payload(&self, height: u64) -> anyhow::Result<Payload> {
+        self.get::<PayloadQueryData>(&format!("availability/payload/{height}"))
+            .map(|data| data.data)
+    }
+
+    /// Fetch the VID common data of the block at `height`.
+    pub fn vid_common(&self, height: u64) -> anyhow::Result<VidCommonQueryData> {
+        self.get(&format!("availability/vid/common/{height}"))
+    }
+
+    /// Fetch the bytes and proof of namespace `ns_id` in the block at
+    /// `height`, or `None` if the namespace is absent from this block.
+    pub fn namespace_proof(
+        &self,
+        height: u64,
+        ns_id: u32,
+    ) -> anyhow::Result<Option<NamespaceProof>> {
+        self.get::<NamespaceProofQueryData>(&format!(
+            "availability/block/{height}/namespace/{ns_id}"
+        ))
+        .map(|data| data.proof)
+    }
+
+    /// Fetch the membership proof of the block at `height` in the block Merkle
+    /// tree containing the first `bmt_height` blocks.
+    pub fn block_merkle_proof(
+        &self,
+        bmt_height: u64,
+        height: u64,
+    ) -> anyhow::Result<BlockMerkleTreeProof> {
+        self.get(&format!("block-state/{bmt_height}/{height}"))
+    }
+
+    /// Fetch the bytes of namespace `ns_id` in the block at `height` (empty if
+    /// absent) and their derivation proof, against the block Merkle tree
+    /// containing the first `bmt_height` blocks.
+    pub fn block_derivation_proof(
+        &self,
+        height: u64,
+        ns_id: u32,
+        bmt_height: u64,
+    ) -> anyhow::Result<(Vec<u8>, BlockDerivationProof)> {
+        let block_header = self.header(height)?;
+        let vid_common = self.vid_common(height)?;
+        ensure!(
+            vid_common.block_hash == block_header.commit()
+                && &vid_common.payload_hash == block_header.payload_commitment(),
+            "VID common data served for another block than the header at {height}"
+        );
+        let vid_common = vid_common.common;
+        let bmt_proof = self.block_merkle_proof(bmt_height, height)?;
+        let (ns_payload, ns_proof) = match self.namespace_proof(height, ns_id)? {
+            Some(proof) => (proof.ns_payload, Some(proof.ns_proof)),
+            None => (vec![], None),
+        };
+        Ok((
+            ns_payload,
+            BlockDerivationProof {
+                bmt_proof,
+                block_header,
+                vid_common,
+                ns_proof,
+            },
+        ))
+    }
+
+    /// Fetch the inclusion proof of the transactions of namespace `ns_id` in
+    /// the block at `height`, against the block Merkle tree committed in the
+    /// header at `bmt_height`, which is returned as well.
+    pub fn tx_inclusion_proof(
+        &self,
+        height: u64,
+        ns_id: u32,
+        bmt_height: u64,
+    ) -> anyhow::Result<(BlockMerkleCommitment, TxInclusionProof)> {
+        ensure!(
+            height < bmt_height,
+            "block {height} is not committed in the block Merkle tree at {bmt_height}"
+        );
+        let bmt_commitment = *self.header(bmt_height)?.block_merkle_tree_root();
+        let (ns_payload, block_proof) = self.block_derivation_proof(height, ns_id, bmt_height)?;
+        Ok((
+            bmt_commitment,
+            TxInclusionProof {
+                ns_payload,
+                block_proof,
+            },
+        ))
+    }
+
+    /// Fetch all the data needed to derive the transactions of namespace
+    /// `ns_id` from blocks in `heights`, against the block Merkle tree
+    /// committed in the header at `bmt_height`.
+    pub fn derivation_batch(
+        &self,
+        heights: RangeInclusive<u64>,
+        ns_id: u32,
+        bmt_height: u64,
+    ) -> anyhow::Result<DerivationBatch> {
+        ensure!(
+            !heights.is_empty() && *heights.end() < bmt_height,
+            "blocks {heights:?} are not all committed in the block Merkle tree at {bmt_height}"
+        );
+        // The header at `bmt_height` commits to all blocks before it
+        let bmt_commitment = *self.header(bmt_height)?.block_merkle_tree_root();
+
+        let mut rollup_txs = vec![];
+        let mut block_derivation_proofs = vec![];
+        for height in heights {
+            let (mut ns_payload, proof) = self.block_derivation_proof(height, ns_id, bmt_height)?;
+            block_derivation_proofs
+                .push((rollup_txs.len()..rollup_txs.len() + ns_payload.len(), proof));
+            rollup_txs.append(&mut ns_payload);
+        }
+
+        Ok(DerivationBatch {
+            rollup_txs: Payload(rollup_txs),
+            bmt_commitment,
+            block_derivation_proofs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryServiceClient;
+    use crate::{
+        mock::test_vid_param,
+        mock_server::{MockChain, MockQueryService},
+    };
+    use espresso_derivation_utils::{
+        derivation::DerivationError,
+        inclusion::{tx_hash, verify_tx_inclusion},
+        ns_payload::NsPayload,
+    };
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_derivation_batch_from_query_service() {
+        let mut rng = rand::rngs::StdRng::from_seed([1u8; 32]);
+        let vid_param = test_vid_param(&mut rng);
+        let ns_id = 42;
+        let num_blocks = 3;
+        // The second block doesn't contain the namespace
+        let chain = MockChain::new(&vid_param, ns_id, num_blocks + 1, &mut rng);
+        assert!(chain.blocks[1].ns_proof.is_none());
+        let expected_txs: Vec<u8> = chain.blocks[..num_blocks as usize]
+            .iter()
+            .flat_map(|block| block.ns_payload.clone())
+            .collect();
+        let expected_payload = chain.blocks[0].payload.0.clone();
+        let service = MockQueryService::spawn(chain);
+
+        let client = QueryServiceClient::new(service.url());
+        assert_eq!(client.payload(0).unwrap().0, expected_payload);
+        let batch = client
+            .derivation_batch(0..=num_blocks - 1, ns_id, num_blocks)
+            .unwrap();
+        assert_eq!(batch.rollup_txs.0, expected_txs);
+        assert_eq!(batch.block_derivation_proofs.len(), num_blocks as usize);
+
+        let public_inputs = batch.into_inputs(vid_param, ns_id, None).verify().unwrap();
+        assert_eq!(public_inputs.start_height, 0);
+        assert_eq!(public_inputs.end_height, num_blocks - 1);
+
+        // Blocks not yet committed in the block Merkle tree are rejected
+        assert!(client
+            .derivation_batch(0..=num_blocks, ns_id, num_blocks)
+            .is_err());
+        // So are missing blocks
+        assert!(client.header(num_blocks + 1).is_err());
+    }
+
+    #[test]
+    fn test_tx_inclusion_from_query_service() {
+        let mut rng = rand::rngs::StdRng::from_seed([3u8; 32]);
+        let vid_param = test_vid_param(&mut rng);
+        let ns_id = 42;
+        let chain = MockChain::new(&vid_param, ns_id, 3, &mut rng);
+        let ns_payload = chain.blocks[2].ns_payload.clone();
+        let service = MockQueryService::spawn(chain);
+        let client = QueryServiceClient::new(service.url());
+
+        let num_txs = NsPayload(&ns_payload).num_txs();
+        let (bmt_commitment, proof) = client.tx_inclusion_proof(2, ns_id, 3).unwrap();
+        for tx_index in 0..num_txs {
+            let public_inputs =
+                verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, tx_index, &proof).unwrap();
+            assert_eq!(public_inputs.height, 2);
+            assert_eq!(public_inputs.tx_index, tx_index);
+            assert_eq!(
+                public_inputs.tx_hash,
+                tx_hash(NsPayload(&ns_payload).tx(tx_index).unwrap())
+            );
+        }
+        assert_eq!(
+            verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, num_txs, &proof).unwrap_err(),
+            DerivationError::TxNotFound {
+                height: 2,
+                tx_index: num_txs
+            }
+        );
+
+        // Nothing is included in a block without the namespace
+        let (bmt_commitment, proof) = client.tx_inclusion_proof(1, ns_id, 3).unwrap();
+        assert!(proof.ns_payload.is_empty());
+        assert_eq!(
+            verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, 0, &proof).unwrap_err(),
+            DerivationError::TxNotFound {
+                height: 1,
+                tx_index: 0
+            }
+        );
+
+        // Tampering with the namespace bytes is caught
+        let (bmt_commitment, mut proof) = client.tx_inclusion_proof(2, ns_id, 3).unwrap();
+        *proof.ns_payload.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verify_tx_inclusion(&vid_param, ns_id, bmt_commitment, 0, &proof).unwrap_err(),
+            DerivationError::NsProofFailure { height: 2 }
+        );
+    }
+
+    #[test]
+    fn test_non_inclusion_from_query_service() {
+        let mut rng = rand::rngs::StdRng::from_seed([4u8; 32]);
+        let vid_param = test_vid_param(&mut rng);
+        let ns_id = 42;
+        let chain = MockChain::new(&vid_param, ns_id, 5, &mut rng);
+        let included_tx = NsPayload(&chain.blocks[3].ns_payload)
+            .tx(0)
+            .unwrap()
+            .to_vec();
+        let service = MockQueryService::spawn(chain);
+        let client = QueryServiceClient::new(service.url());
+        let inputs = client
+            .derivation_batch(1..=3, ns_id, 4)
+            .unwrap()
+            .into_inputs(vid_param, ns_id, None);
+
+        let public_inputs = inputs.verify_non_inclusion(tx_hash(b"censored")).unwrap();
+        assert!(public_inputs.not_included);
+        assert_eq!(public_inputs.derivation.start_height, 1);
+        assert_eq!(public_inputs.derivation.end_height, 3);
+
+        let public_inputs = inputs.verify_non_inclusion(tx_hash(&included_tx)).unwrap();
+        assert!(!public_inputs.not_included);
+    }
+}
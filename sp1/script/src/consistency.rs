@@ -0,0 +1,188 @@
+//! Composing a derivation proof, a VM execution proof and a light client proof
+//! with the consistency program under `sp1/consistency`.
+
+use sp1_sdk::{HashableKey, SP1Proof, SP1ProofWithPublicValues, SP1Stdin, SP1VerifyingKey};
+
+/// Write the inputs of the consistency program, given the compressed
+/// derivation proof (in light client mode), VM execution proof and light
+/// client proof with their verifying keys.
+///
+/// # Panics
+/// If any of the proofs is not compressed.
+pub fn write_consistency_inputs(
+    derivation: (&SP1ProofWithPublicValues, &SP1VerifyingKey),
+    vm: (&SP1ProofWithPublicValues, &SP1VerifyingKey),
+    light_client: (&SP1ProofWithPublicValues, &SP1VerifyingKey),
+    stdin: &mut SP1Stdin,
+) {
+    // Public values and keys first, in the order the program reads them
+    for (proof, vk) in [derivation, vm, light_client] {
+        stdin.write(&vk.hash_u32());
+        stdin.write(&proof.public_values.to_vec());
+    }
+    // Then the proofs, in the order the program verifies them
+    for (proof, vk) in [derivation, vm, light_client] {
+        let SP1Proof::Compressed(compressed) = proof.proof.clone() else {
+            panic!("sub-proof is not compressed");
+        };
+        stdin.write_proof(compressed, vk.vk.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_consistency_inputs;
+    use crate::{
+        inputs::write_inputs,
+        mock::{mock_inputs, test_vid_param},
+    };
+    use espresso_derivation_utils::{
+        abi::{
+            decode_consistency_public_values, decode_light_client_public_values,
+            ConsistencyPublicValuesStruct,
+        },
+        block::payload::Payload,
+        consistency::{
+            check_consistency, ConsistencyError, ConsistencyPublicInputs, VmPublicInputs,
+        },
+        light_client::LightClientState,
+        DerivationMode,
+    };
+    use jf_merkle_tree::MerkleCommitment;
+    use primitive_types::H256;
+    use rand::SeedableRng;
+    use sp1_sdk::{ProverClient, SP1Stdin};
+
+    const DERIVATION_ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
+    const CONSISTENCY_ELF: &[u8] =
+        include_bytes!("../../consistency/elf/riscv32im-succinct-zkvm-elf");
+    const MOCK_VM_ELF: &[u8] = include_bytes!("../../mock-vm/elf/riscv32im-succinct-zkvm-elf");
+    const MOCK_LIGHT_CLIENT_ELF: &[u8] =
+        include_bytes!("../../mock-light-client/elf/riscv32im-succinct-zkvm-elf");
+
+    /// Stdins of the derivation program over the mock batch in light client
+    /// mode, of the mock VM over `vm_txs` (the batch transactions if `None`),
+    /// and of the mock light client over `proven_state` (the state of the batch
+    /// if `None`).
+    fn sub_program_stdins(
+        vm_txs: Option<Vec<u8>>,
+        proven_state: Option<LightClientState>,
+    ) -> [SP1Stdin; 3] {
+        let mut rng = rand::rngs::StdRng::from_seed([3u8; 32]);
+        let inputs = mock_inputs(test_vid_param(&mut rng));
+        let state = LightClientState::new(1, inputs.bmt_commitment.size(), &inputs.bmt_commitment);
+
+        let mut derivation = SP1Stdin::new();
+        write_inputs(
+            &inputs,
+            DerivationMode::LightClient { state },
+            &mut derivation,
+        );
+
+        let mut vm = SP1Stdin::new();
+        vm.write(&vm_txs.map_or(inputs.rollup_txs, Payload));
+        vm.write(&H256::zero());
+
+        let mut light_client = SP1Stdin::new();
+        light_client.write(&proven_state.unwrap_or(state));
+
+        [derivation, vm, light_client]
+    }
+
+    /// Execute the three sub-programs, then check the consistency of their
+    /// public values natively, as the consistency program does after verifying
+    /// their proofs.
+    fn execute(
+        vm_txs: Option<Vec<u8>>,
+        proven_state: Option<LightClientState>,
+    ) -> Result<ConsistencyPublicInputs, ConsistencyError> {
+        let client = ProverClient::new();
+        let [derivation, vm, light_client] = sub_program_stdins(vm_txs, proven_state);
+
+        let (derivation, _) = client.execute(DERIVATION_ELF, derivation).run().unwrap();
+        let derivation = decode_light_client_public_values(derivation.as_slice()).unwrap();
+        let (mut vm, _) = client.execute(MOCK_VM_ELF, vm).run().unwrap();
+        let vm: VmPublicInputs = vm.read();
+        let (mut light_client, _) = client
+            .execute(MOCK_LIGHT_CLIENT_ELF, light_client)
+            .run()
+            .unwrap();
+        let light_client_state: LightClientState = light_client.read();
+
+        check_consistency([0; 8], [1; 8], [2; 8], derivation, vm, light_client_state)
+    }
+
+    /// Prove the three sub-programs, then execute the consistency program over
+    /// their proofs.
+    fn compose(
+        vm_txs: Option<Vec<u8>>,
+        proven_state: Option<LightClientState>,
+    ) -> anyhow::Result<ConsistencyPublicValuesStruct> {
+        let client = ProverClient::new();
+        let [derivation, vm, light_client] = sub_program_stdins(vm_txs, proven_state);
+
+        let (derivation_pk, derivation_vk) = client.setup(DERIVATION_ELF);
+        let derivation = client
+            .prove(&derivation_pk, derivation)
+            .compressed()
+            .run()?;
+        let (vm_pk, vm_vk) = client.setup(MOCK_VM_ELF);
+        let vm = client.prove(&vm_pk, vm).compressed().run()?;
+        let (light_client_pk, light_client_vk) = client.setup(MOCK_LIGHT_CLIENT_ELF);
+        let light_client = client
+            .prove(&light_client_pk, light_client)
+            .compressed()
+            .run()?;
+
+        let mut stdin = SP1Stdin::new();
+        write_consistency_inputs(
+            (&derivation, &derivation_vk),
+            (&vm, &vm_vk),
+            (&light_client, &light_client_vk),
+            &mut stdin,
+        );
+        let (public_values, _) = client.execute(CONSISTENCY_ELF, stdin).run()?;
+        Ok(decode_consistency_public_values(public_values.as_slice())?)
+    }
+
+    #[test]
+    fn test_consistency_of_mock_executions() {
+        let public_inputs = execute(None, None).unwrap();
+        assert_eq!(public_inputs.prev_state_root, H256::zero());
+        assert_eq!(public_inputs.light_client_state.view_num, 1);
+
+        // The VM executed other transactions
+        assert!(matches!(
+            execute(Some(vec![1, 2, 3]), None),
+            Err(ConsistencyError::RollupCommitMismatch { .. })
+        ));
+
+        // The light client proved another state
+        let mut rng = rand::rngs::StdRng::from_seed([3u8; 32]);
+        let inputs = mock_inputs(test_vid_param(&mut rng));
+        let other_state =
+            LightClientState::new(2, inputs.bmt_commitment.size(), &inputs.bmt_commitment);
+        assert!(matches!(
+            execute(None, Some(other_state)),
+            Err(ConsistencyError::LightClientStateMismatch { .. })
+        ));
+    }
+
+    #[test]
+    #[ignore = "generates several SP1 proofs, requires the ELFs built by `just sp1-build`"]
+    fn test_consistency_of_mock_proofs() {
+        let public_values = compose(None, None).unwrap();
+        assert_eq!(public_values.prevStateRoot.0, [0u8; 32]);
+        assert_eq!(public_values.lightClientState.viewNum, 1);
+
+        // The VM executed other transactions
+        assert!(compose(Some(vec![1, 2, 3]), None).is_err());
+
+        // The light client proved another state
+        let mut rng = rand::rngs::StdRng::from_seed([3u8; 32]);
+        let inputs = mock_inputs(test_vid_param(&mut rng));
+        let other_state =
+            LightClientState::new(2, inputs.bmt_commitment.size(), &inputs.bmt_commitment);
+        assert!(compose(None, Some(other_state)).is_err());
+    }
+}
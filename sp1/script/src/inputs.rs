@@ -0,0 +1,389 @@
+//! Loading and saving inputs of the derivation program.
+//!
+//! An input set is either a single file holding the whole [`DerivationInputs`],
+//! or a directory with one file per input:
+//! - `rollup_txs.bin`: raw bytes of the rollup payload
+//! - `ns_id.json`: namespace ID of the rollup
+//! - `bmt_commitment.json`: the block Merkle tree commitment
+//! - `prev_batch.json` (optional): where the previous batch ended
+//! - `block_derivation_proofs.{json,bin}`: the list of `(range, proof)` pairs
+//! - `vid_param.{json,bin}`: the VID public parameter
+//!
+//! Files ending with `.json` are read as JSON, and files ending with `.bin` in
+//! the compact binary (bincode) form. Inputs saved before the VID public
+//! parameter could be truncated, holding the full SRS alone, are still read.
+//!
+//! Once loaded, [`write_inputs`] hands them to the derivation program.
+
+use anyhow::{anyhow, bail, Context};
+use ark_serialize::CanonicalDeserialize;
+use espresso_derivation_utils::{
+    block::{
+        header::BlockMerkleCommitment,
+        payload::{Payload, VidParam},
+    },
+    BlockDerivationProof, DerivationInputs, DerivationMode, PrevBatch,
+};
+use jf_pcs::prelude::UnivariateUniversalParams;
+use serde::{
+    de::{DeserializeOwned, Error},
+    Deserialize, Deserializer, Serialize,
+};
+use sp1_sdk::SP1Stdin;
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Encoding of an input file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputsFormat {
+    /// JSON, human readable
+    Json,
+    /// Compact binary form using bincode
+    Binary,
+}
+
+impl InputsFormat {
+    /// Infer the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("bin") => Ok(Self::Binary),
+            _ => Err(anyhow!(
+                "unknown input format for {}, expecting `.json` or `.bin`",
+                path.display()
+            )),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> anyhow::Result<T> {
+        Ok(match self {
+            Self::Json => serde_json::from_slice(bytes)?,
+            Self::Binary => bincode::deserialize(bytes)?,
+        })
+    }
+
+    fn encode<T: Serialize>(self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(value)?,
+            Self::Binary => bincode::serialize(value)?,
+        })
+    }
+}
+
+/// VID public parameter in the legacy bincode form, the uncompressed full SRS
+/// alone. JSON falls back to it within [`VidParam`]'s deserialization.
+struct LegacyVidParam(VidParam);
+
+impl<'de> Deserialize<'de> for LegacyVidParam {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <Vec<u8> as Deserialize>::deserialize(deserializer)?;
+        let srs = UnivariateUniversalParams::deserialize_uncompressed_unchecked(&*bytes)
+            .map_err(|e| D::Error::custom(format!("{e:?}")))?;
+        Ok(Self(VidParam::new(srs)))
+    }
+}
+
+impl From<LegacyVidParam> for VidParam {
+    fn from(param: LegacyVidParam) -> Self {
+        param.0
+    }
+}
+
+/// [`DerivationInputs`] with the legacy VID public parameter.
+#[derive(Deserialize)]
+struct LegacyDerivationInputs {
+    rollup_txs: Payload,
+    vid_param: LegacyVidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: Vec<(Range<usize>, BlockDerivationProof)>,
+}
+
+impl From<LegacyDerivationInputs> for DerivationInputs {
+    fn from(inputs: LegacyDerivationInputs) -> Self {
+        Self {
+            rollup_txs: inputs.rollup_txs,
+            vid_param: inputs.vid_param.0,
+            ns_id: inputs.ns_id,
+            bmt_commitment: inputs.bmt_commitment,
+            prev_batch: inputs.prev_batch,
+            block_derivation_proofs: inputs.block_derivation_proofs,
+        }
+    }
+}
+
+/// Read and decode a file, with the format inferred from its extension.
+fn read_file<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    read_file_or_legacy::<T, T>(path)
+}
+
+/// Read and decode a file as `T`, or as the legacy form `L` if it is in
+/// bincode.
+fn read_file_or_legacy<T: DeserializeOwned, L: DeserializeOwned + Into<T>>(
+    path: &Path,
+) -> anyhow::Result<T> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let format = InputsFormat::from_path(path)?;
+    format
+        .decode(&bytes)
+        .or_else(|err| match format {
+            InputsFormat::Binary => format.decode::<L>(&bytes).map(Into::into).map_err(|_| err),
+            InputsFormat::Json => Err(err),
+        })
+        .with_context(|| format!("failed to decode {}", path.display()))
+}
+
+/// Find `{name}.json` or `{name}.bin` under `dir`.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    ["json", "bin"]
+        .into_iter()
+        .map(|ext| dir.join(format!("{name}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// Load an input set from a directory or a single file.
+pub fn load_inputs(path: &Path) -> anyhow::Result<DerivationInputs> {
+    if !path.is_dir() {
+        return read_file_or_legacy::<_, LegacyDerivationInputs>(path);
+    }
+
+    let required = |name: &str| {
+        find_file(path, name).ok_or_else(|| {
+            anyhow!(
+                "missing `{name}.json` or `{name}.bin` in {}",
+                path.display()
+            )
+        })
+    };
+    let rollup_txs_path = path.join("rollup_txs.bin");
+    let rollup_txs = Payload(
+        std::fs::read(&rollup_txs_path)
+            .with_context(|| format!("failed to read {}", rollup_txs_path.display()))?,
+    );
+    let prev_batch = match find_file(path, "prev_batch") {
+        Some(file) => read_file(&file)?,
+        None => None,
+    };
+    Ok(DerivationInputs {
+        rollup_txs,
+        vid_param: read_file_or_legacy::<_, LegacyVidParam>(&required("vid_param")?)?,
+        ns_id: read_file(&required("ns_id")?)?,
+        bmt_commitment: read_file(&required("bmt_commitment")?)?,
+        prev_batch,
+        block_derivation_proofs: read_file(&required("block_derivation_proofs")?)?,
+    })
+}
+
+/// Save an input set to a single file, with the format inferred from its
+/// extension.
+pub fn save_inputs(inputs: &DerivationInputs, path: &Path) -> anyhow::Result<()> {
+    let bytes = InputsFormat::from_path(path)?.encode(inputs)?;
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Load an input set and check it natively, so that invalid inputs are
+/// rejected before being handed to the prover.
+pub fn load_and_validate_inputs(path: &Path) -> anyhow::Result<DerivationInputs> {
+    let inputs = load_inputs(path)?;
+    if let Err(err) = inputs.verify() {
+        bail!("invalid inputs in {}: {err}", path.display());
+    }
+    Ok(inputs)
+}
+
+/// Write all inputs in the order the derivation program reads them, except
+/// the blob setup read last in blob mode.
+pub fn write_inputs(inputs: &DerivationInputs, mode: DerivationMode, stdin: &mut SP1Stdin) {
+    stdin.write(&mode);
+    stdin.write(&inputs.rollup_txs);
+    // The full SRS is too large for the guest, only the needed powers are read
+    stdin.write(
+        &inputs.vid_param.truncate_for(
+            inputs
+                .block_derivation_proofs
+                .iter()
+                .map(|(_, p)| &p.vid_common),
+        ),
+    );
+    stdin.write(&inputs.ns_id);
+    stdin.write(&inputs.bmt_commitment);
+    stdin.write(&inputs.prev_batch);
+    stdin.write(&inputs.block_derivation_proofs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_inputs, save_inputs, InputsFormat};
+    use crate::mock::{mock_inputs, test_vid_param};
+    use ark_serialize::CanonicalSerialize;
+    use espresso_derivation_utils::{DerivationInputs, PrevBatch};
+    use primitive_types::H256;
+    use rand::SeedableRng;
+    use std::path::{Path, PathBuf};
+
+    fn inputs() -> DerivationInputs {
+        let mut rng = rand::rngs::StdRng::from_seed([2u8; 32]);
+        mock_inputs(test_vid_param(&mut rng))
+    }
+
+    /// Inputs are compared through their binary form.
+    fn assert_same(a: &DerivationInputs, b: &DerivationInputs) {
+        assert_eq!(
+            bincode::serialize(a).unwrap(),
+            bincode::serialize(b).unwrap()
+        );
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("espresso-derivation-inputs-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write `inputs` to `dir` with one file per input, in `format`.
+    fn write_dir(inputs: &DerivationInputs, dir: &Path, format: InputsFormat) {
+        let ext = match format {
+            InputsFormat::Json => "json",
+            InputsFormat::Binary => "bin",
+        };
+        std::fs::write(dir.join("rollup_txs.bin"), &inputs.rollup_txs.0).unwrap();
+        let write = |name: &str, bytes: Vec<u8>| {
+            std::fs::write(dir.join(format!("{name}.{ext}")), bytes).unwrap()
+        };
+        write("vid_param", format.encode(&inputs.vid_param).unwrap());
+        write(
+            "block_derivation_proofs",
+            format.encode(&inputs.block_derivation_proofs).unwrap(),
+        );
+        std::fs::write(
+            dir.join("ns_id.json"),
+            serde_json::to_vec(&inputs.ns_id).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("bmt_commitment.json"),
+            serde_json::to_vec(&inputs.bmt_commitment).unwrap(),
+        )
+        .unwrap();
+        if inputs.prev_batch.is_some() {
+            std::fs::write(
+                dir.join("prev_batch.json"),
+                serde_json::to_vec(&inputs.prev_batch).unwrap(),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_single_file_round_trip() {
+        let inputs = inputs();
+        let dir = test_dir("file");
+        for name in ["inputs.json", "inputs.bin"] {
+            let path = dir.join(name);
+            save_inputs(&inputs, &path).unwrap();
+            assert_same(&load_inputs(&path).unwrap(), &inputs);
+        }
+
+        // Unknown extensions are rejected both ways
+        assert!(save_inputs(&inputs, &dir.join("inputs.txt")).is_err());
+        std::fs::copy(dir.join("inputs.json"), dir.join("inputs.txt")).unwrap();
+        assert!(load_inputs(&dir.join("inputs.txt")).is_err());
+        // So is a file in the wrong format
+        std::fs::copy(dir.join("inputs.json"), dir.join("json.bin")).unwrap();
+        assert!(load_inputs(&dir.join("json.bin")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_directory_layout() {
+        let mut inputs = inputs();
+        for format in [InputsFormat::Json, InputsFormat::Binary] {
+            // Without `prev_batch`
+            let dir = test_dir("dir");
+            write_dir(&inputs, &dir, format);
+            let loaded = load_inputs(&dir).unwrap();
+            assert!(loaded.prev_batch.is_none());
+            assert_same(&loaded, &inputs);
+
+            // With `prev_batch`
+            inputs.prev_batch = Some(PrevBatch {
+                end_height: 41,
+                accumulator: H256::repeat_byte(7),
+            });
+            write_dir(&inputs, &dir, format);
+            assert_same(&load_inputs(&dir).unwrap(), &inputs);
+            inputs.prev_batch = None;
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        // Missing files
+        for missing in [
+            "rollup_txs.bin",
+            "vid_param.json",
+            "ns_id.json",
+            "bmt_commitment.json",
+            "block_derivation_proofs.json",
+        ] {
+            let dir = test_dir("missing");
+            write_dir(&inputs, &dir, InputsFormat::Json);
+            std::fs::remove_file(dir.join(missing)).unwrap();
+            assert!(load_inputs(&dir).is_err(), "{missing} is required");
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_legacy_vid_param() {
+        let inputs = inputs();
+        assert!(inputs.vid_param.omitted_chunks.is_empty());
+        let mut srs = Vec::new();
+        inputs
+            .vid_param
+            .srs
+            .serialize_uncompressed(&mut srs)
+            .unwrap();
+        let dir = test_dir("legacy");
+
+        // Single files, with the full SRS alone in place of the VID parameter
+        let legacy = bincode::serialize(&(
+            &inputs.rollup_txs,
+            &srs,
+            inputs.ns_id,
+            &inputs.bmt_commitment,
+            &inputs.prev_batch,
+            &inputs.block_derivation_proofs,
+        ))
+        .unwrap();
+        std::fs::write(dir.join("inputs.bin"), legacy).unwrap();
+        let mut legacy = serde_json::to_value(&inputs).unwrap();
+        legacy["vid_param"] = serde_json::to_value(&srs).unwrap();
+        std::fs::write(dir.join("inputs.json"), legacy.to_string()).unwrap();
+        for name in ["inputs.json", "inputs.bin"] {
+            assert_same(&load_inputs(&dir.join(name)).unwrap(), &inputs);
+        }
+
+        // Directory layout
+        for format in [InputsFormat::Json, InputsFormat::Binary] {
+            let dir = test_dir("legacy-dir");
+            write_dir(&inputs, &dir, format);
+            let _ = std::fs::remove_file(dir.join("vid_param.json"));
+            let _ = std::fs::remove_file(dir.join("vid_param.bin"));
+            let path = match format {
+                InputsFormat::Json => dir.join("vid_param.json"),
+                InputsFormat::Binary => dir.join("vid_param.bin"),
+            };
+            std::fs::write(path, format.encode(&srs).unwrap()).unwrap();
+            assert_same(&load_inputs(&dir).unwrap(), &inputs);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
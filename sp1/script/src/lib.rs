@@ -0,0 +1,8 @@
+//! Host-side tooling for proving Espresso derivation with SP1.
+
+pub mod client;
+pub mod consistency;
+pub mod inputs;
+pub mod mock;
+pub mod mock_server;
+pub mod srs;
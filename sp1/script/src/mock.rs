@@ -0,0 +1,349 @@
+//! Mock Espresso blocks and derivation inputs for demo and tests.
+
+use committable::Committable;
+use espresso_derivation_utils::{
+    block::{
+        header::{BlockHeader, BlockMerkleTree},
+        payload::{
+            vid_scheme, AdvzCommitment, AdvzCommon, AdvzNsProof, NsProof, Payload, Vid,
+            VidCommitment, VidCommon, VidParam,
+        },
+    },
+    ns_payload::NsPayload,
+    ns_table::NsTable,
+    BlockDerivationProof, DerivationInputs,
+};
+use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleTreeScheme};
+use jf_vid::{payload_prover::PayloadProver, VidScheme};
+use rand::{Rng, RngCore, SeedableRng};
+
+/// low degree for demo only
+pub const SRS_DEGREE: usize = 8usize;
+/// payload bytes for each block shouldn't exceed max size
+/// during encoding, every 30 bytes is converted to a 254-bit field element
+pub const MAX_PAYLOAD_BYTES_PER_BLOCK: usize = SRS_DEGREE * 30;
+/// number of storage node for VID
+pub const NUM_STORAGE_NODES: u32 = 10;
+/// produce derivation proof for a batch of espresso blocks
+/// the block in the middle of the batch doesn't contain the rollup's namespace
+pub const NUM_BLOCKS: u64 = 5;
+
+/// Mock the payload of a namespace with a few random transactions, fitting in
+/// `MAX_PAYLOAD_BYTES_PER_BLOCK` bytes.
+pub fn mock_ns_payload<R: RngCore>(rng: &mut R) -> Vec<u8> {
+    const MAX_NUM_TXS: usize = 4;
+    let max_tx_len = (MAX_PAYLOAD_BYTES_PER_BLOCK - 4 * (MAX_NUM_TXS + 1)) / MAX_NUM_TXS;
+    let txs: Vec<Vec<u8>> = (0..rng.gen_range(1..=MAX_NUM_TXS))
+        .map(|_| {
+            let mut tx = vec![0u8; rng.gen_range(1..=max_tx_len)];
+            rng.fill_bytes(&mut tx);
+            tx
+        })
+        .collect();
+    NsPayload::build(&txs)
+}
+
+/// Mock an Espresso block at height `idx` containing `ns_payload` in namespace
+/// `ns_id`. An empty `ns_payload` yields a block without this namespace.
+///
+/// Return the block header, the whole block payload, the VID common data and
+/// the namespace proof (if the namespace is present).
+pub fn mock_block<R: RngCore>(
+    idx: u64,
+    ns_id: u32,
+    ns_payload: &[u8],
+    vid: &mut Vid,
+    rng: &mut R,
+) -> (BlockHeader, Payload, VidCommon, Option<NsProof>) {
+    // This is a tweak from an actual block header in Espresso's staging testnet
+    let mut header: BlockHeader = serde_json::from_str(
+        r#"{
+            "chain_config": {
+                "chain_config": {
+                    "Left": {
+                        "chain_id": "888888888",
+                        "max_block_size": "30000000",
+                        "base_fee": "0",
+                        "fee_contract": null,
+                        "fee_recipient": "0x0000000000000000000000000000000000000000"
+                    }
+                }
+            },
+            "height": 69781,
+            "timestamp": 1720789795,
+            "l1_head": 5113,
+            "l1_finalized": {
+                "number": 5088,
+                "timestamp": "0x669129ec",
+                "hash": "0xfc4249b13292d2617cc0dec8b0a9a666491d5fecdfe536c929207847364b2b60"
+            },
+            "payload_commitment": "HASH~KpvHX4MuDuZKk10QJctEoUj-fump6NIAO8fJ048RwNJo",
+            "builder_commitment": "BUILDER_COMMITMENT~tEvs0rxqOiMCvfe2R0omNNaphSlUiEDrb2q0IZpRcgA_",
+            "ns_table": {
+                "bytes": "AQAAAB0AAAALAAAA"
+            },
+            "block_merkle_tree_root": "MERKLE_COMM~02gWBSt2tcz9XfOOO6xEVicluWIIP95BW8I11f2graggAAAAAAAAAJUQAQAAAAAAUQ",
+            "fee_merkle_tree_root": "MERKLE_COMM~yB4_Aqa35_PoskgTpcCR1oVLh6BUdLHIs7erHKWi-usUAAAAAAAAAAEAAAAAAAAAJg",
+            "fee_info": {
+                "account": "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f",
+                "amount": "0"
+            },
+            "builder_signature": {
+                "r": "0x6291b473fdac85b9ce7b40b530ea4173ac6e71fd29acffc3cbc97ae637d4404d",
+                "s": "0x3178fe07d5071df7a7ce4106e6e1e3727aa6edc458db03d1774948bdec32eac6",
+                "v": 28
+            }
+         }"#,
+    ).unwrap();
+
+    // Mock a height, matching the block's position in the block Merkle tree
+    *header.height_mut() = idx;
+
+    // A block without this namespace
+    if ns_payload.is_empty() {
+        let payload_size = rng.gen_range(1..MAX_PAYLOAD_BYTES_PER_BLOCK);
+        let mut payload = vec![0u8; payload_size];
+        rng.fill_bytes(&mut payload);
+
+        let vid_disperse = vid.disperse(&payload).unwrap();
+        *header.payload_commitment_mut() = VidCommitment::V0(AdvzCommitment(vid_disperse.commit));
+        *header.ns_table_mut() = NsTable::mock_ns_table(&[(
+            ns_id.wrapping_add(rng.gen_range(1..u32::MAX)),
+            payload_size as u32,
+        )]);
+        return (
+            header,
+            Payload(payload),
+            VidCommon::V0(AdvzCommon(vid_disperse.common)),
+            None,
+        );
+    }
+
+    // Mock payload, with two other namespaces of random bytes around the
+    // rollup's one. Namespace IDs must be increasing in the namespace table.
+    let mut ns_ids = vec![ns_id];
+    while ns_ids.len() < 3 {
+        let other_id = rng.next_u32();
+        if !ns_ids.contains(&other_id) {
+            ns_ids.push(other_id);
+        }
+    }
+    ns_ids.sort_unstable();
+
+    let mut payload = vec![];
+    let mut ns_table_entries = vec![];
+    let mut ns_range = 0..0;
+    for id in ns_ids {
+        let start = payload.len();
+        if id == ns_id {
+            payload.extend_from_slice(ns_payload);
+            ns_range = start..payload.len();
+        } else {
+            let mut other_ns_payload = vec![0u8; rng.gen_range(1..=4 * ns_payload.len())];
+            rng.fill_bytes(&mut other_ns_payload);
+            payload.append(&mut other_ns_payload);
+        }
+        ns_table_entries.push((id, payload.len() as u32));
+    }
+
+    // Mock VID information
+    let vid_disperse = vid.disperse(&payload).unwrap();
+    let vid_common = VidCommon::V0(AdvzCommon(vid_disperse.common));
+    let vid_commitment = VidCommitment::V0(AdvzCommitment(vid_disperse.commit));
+    // Update the payload commitment
+    *header.payload_commitment_mut() = vid_commitment;
+    // Update the namespace table
+    *header.ns_table_mut() = NsTable::mock_ns_table(&ns_table_entries);
+
+    // Namespace proof
+    let ns_proof = NsProof::V0(AdvzNsProof(vid.payload_proof(&payload, ns_range).unwrap()));
+
+    (header, Payload(payload), vid_common, Some(ns_proof))
+}
+
+/// Mock the inputs for a batch of `NUM_BLOCKS` Espresso blocks.
+pub fn mock_inputs(vid_param: VidParam) -> DerivationInputs {
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+
+    let ns_id = rng.next_u32();
+    let mut block_merkle_tree = BlockMerkleTree::new(32);
+    let mut rollup_payload = Payload(vec![]);
+    let mut block_proofs = vec![];
+
+    let mut vid = vid_scheme(NUM_STORAGE_NODES, &vid_param);
+
+    for i in 0..NUM_BLOCKS {
+        // pick random transactions for each block, leaving the rollup's
+        // namespace out of one of them
+        let mut block_ns_payload = if i == NUM_BLOCKS / 2 {
+            vec![]
+        } else {
+            mock_ns_payload(&mut rng)
+        };
+        let ns_payload_len = block_ns_payload.len();
+
+        // produce a mock block containing this namespace payload
+        let (header, _, vid_common, ns_proof) =
+            mock_block(i, ns_id, &block_ns_payload, &mut vid, &mut rng);
+
+        // push the block commitment to the BMT
+        block_merkle_tree.push(header.commit()).unwrap();
+
+        // retrieve a merkle proof of this block commitment
+        let (_, bmt_proof) = block_merkle_tree.lookup(i).expect_ok().unwrap();
+
+        // prepare the block derivation proof
+        block_proofs.push((
+            rollup_payload.0.len()..rollup_payload.0.len() + ns_payload_len,
+            BlockDerivationProof {
+                bmt_proof,
+                block_header: header,
+                vid_common,
+                ns_proof,
+            },
+        ));
+        // append to overall rollup-specific payload
+        // (as if filtered from a batch of blocks)
+        rollup_payload.0.append(&mut block_ns_payload);
+    }
+
+    // update all the BMT inclusion proof since new block commitments where
+    // accumulated, and old merkle proofs are holding outdated root
+    for i in 0..NUM_BLOCKS {
+        let (_, bmt_proof) = block_merkle_tree.lookup(i).expect_ok().unwrap();
+        block_proofs.get_mut(i as usize).unwrap().1.bmt_proof = bmt_proof;
+    }
+
+    DerivationInputs {
+        rollup_txs: rollup_payload,
+        vid_param,
+        ns_id,
+        bmt_commitment: block_merkle_tree.commitment(),
+        prev_batch: None,
+        block_derivation_proofs: block_proofs,
+    }
+}
+
+/// Generate a VID public parameter of `SRS_DEGREE` for testing only.
+#[cfg(test)]
+pub(crate) fn test_vid_param<R: RngCore + rand::CryptoRng>(rng: &mut R) -> VidParam {
+    use jf_pcs::prelude::{PolynomialCommitmentScheme, UnivariateKzgPCS};
+
+    VidParam::new(
+        UnivariateKzgPCS::<ark_bn254::Bn254>::gen_srs_for_testing(rng, SRS_DEGREE).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock_inputs, test_vid_param, NUM_BLOCKS};
+    use crate::inputs::write_inputs;
+    use espresso_derivation_utils::{
+        abi::encode_public_values, derivation::DerivationError, DerivationInputs, DerivationMode,
+    };
+    use rand::SeedableRng;
+    use sp1_sdk::{ProverClient, SP1Stdin};
+
+    const DERIVATION_ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
+
+    #[test]
+    fn test_native_verification_matches_program() {
+        let mut rng = rand::rngs::StdRng::from_seed([1u8; 32]);
+        let inputs = mock_inputs(test_vid_param(&mut rng));
+        let public_inputs = inputs.verify().unwrap();
+        assert_eq!(public_inputs.start_height, 0);
+        assert_eq!(public_inputs.end_height, NUM_BLOCKS - 1);
+
+        // The program commits exactly the public inputs returned natively
+        let mut stdin = SP1Stdin::new();
+        write_inputs(&inputs, DerivationMode::Derivation, &mut stdin);
+        let (public_values, _) = ProverClient::new()
+            .execute(DERIVATION_ELF, stdin)
+            .run()
+            .unwrap();
+        assert_eq!(
+            public_values.as_slice(),
+            encode_public_values(&public_inputs).as_slice()
+        );
+    }
+
+    #[test]
+    #[cfg_attr(
+        not(feature = "advz-per-block"),
+        ignore = "ADVZ is only set up with the advz-per-block feature, which the program must be \
+                  built with too"
+    )]
+    fn test_vid_setup_cycles() {
+        let mut rng = rand::rngs::StdRng::from_seed([1u8; 32]);
+        let mut inputs = mock_inputs(test_vid_param(&mut rng));
+        let vid_setup_cycles = |inputs: &DerivationInputs| {
+            let mut stdin = SP1Stdin::new();
+            write_inputs(inputs, DerivationMode::Derivation, &mut stdin);
+            let (_, report) = ProverClient::new()
+                .execute(DERIVATION_ELF, stdin)
+                .run()
+                .unwrap();
+            (
+                report
+                    .cycle_tracker
+                    .get("vid-setup")
+                    .copied()
+                    .unwrap_or_default(),
+                report.total_instruction_count(),
+            )
+        };
+        let (setup, total) = vid_setup_cycles(&inputs);
+
+        // The first block alone, against the same block Merkle tree
+        inputs.block_derivation_proofs.truncate(1);
+        let range = inputs.block_derivation_proofs[0].0.clone();
+        inputs.rollup_txs.0.truncate(range.end);
+        let (setup_once, total_once) = vid_setup_cycles(&inputs);
+        println!(
+            "vid-setup: {setup} cycles out of {total} for {NUM_BLOCKS} blocks, {setup_once} out \
+             of {total_once} for one block, saving {} cycles over a setup per block",
+            (NUM_BLOCKS - 1) * setup_once
+        );
+        // All blocks of the mock batch share their number of storage nodes,
+        // thus ADVZ is set up once rather than for each block
+        assert!(setup_once > 0);
+        assert!(setup < NUM_BLOCKS * setup_once);
+        assert_eq!(setup, setup_once);
+    }
+
+    #[test]
+    fn test_bad_block_reported_at_its_height() {
+        let mut rng = rand::rngs::StdRng::from_seed([1u8; 32]);
+        let mut inputs = mock_inputs(test_vid_param(&mut rng));
+
+        // Tamper with the transactions derived from the block at height 3
+        let (range, proof) = &inputs.block_derivation_proofs[3];
+        assert_eq!(proof.block_header.height(), 3);
+        inputs.rollup_txs.0[range.start] ^= 1;
+        assert_eq!(
+            inputs.verify().unwrap_err(),
+            DerivationError::NsProofFailure { height: 3 }
+        );
+        inputs.rollup_txs.0[range.start] ^= 1;
+
+        // Swap the VID common data of the blocks at heights 1 and 3
+        let swap_vid_common = |inputs: &mut DerivationInputs| {
+            let (left, right) = inputs.block_derivation_proofs.split_at_mut(3);
+            std::mem::swap(&mut left[1].1.vid_common, &mut right[0].1.vid_common);
+        };
+        swap_vid_common(&mut inputs);
+        assert_eq!(
+            inputs.verify().unwrap_err(),
+            DerivationError::VidCommonMismatch { height: 1 }
+        );
+        swap_vid_common(&mut inputs);
+
+        // Swap the headers of the blocks at heights 1 and 4
+        let (left, right) = inputs.block_derivation_proofs.split_at_mut(4);
+        std::mem::swap(&mut left[1].1.block_header, &mut right[0].1.block_header);
+        assert_eq!(
+            inputs.verify().unwrap_err(),
+            DerivationError::PositionMismatch { height: 4, pos: 1 }
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! An in-process mock of the Espresso query service over a simulated chain,
+//! for testing batchers end-to-end without a network.
+//!
+//! Responses use the same endpoints and JSON shapes as [`crate::client`].
+
+use crate::{
+    client::{
+        NamespaceProof, NamespaceProofQueryData, PayloadQueryData, Transaction, VidCommonQueryData,
+    },
+    mock::{mock_block, mock_ns_payload, NUM_STORAGE_NODES},
+};
+use committable::Committable;
+use espresso_derivation_utils::{
+    block::{
+        header::{BlockHeader, BlockMerkleTree, BlockMerkleTreeProof},
+        payload::{vid_scheme, NsProof, Payload, VidCommon, VidParam},
+    },
+    ns_payload::NsPayload,
+};
+use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleTreeScheme};
+use rand::RngCore;
+use std::sync::Arc;
+
+/// A block of the simulated chain.
+#[derive(Debug)]
+pub struct MockBlock {
+    pub header: BlockHeader,
+    pub payload: Payload,
+    pub vid_common: VidCommon,
+    /// Bytes of the rollup's namespace, empty if absent from this block
+    pub ns_payload: Vec<u8>,
+    /// Namespace proof of `ns_payload`, `None` if absent from this block
+    pub ns_proof: Option<NsProof>,
+}
+
+/// A simulated Espresso chain carrying transactions of a single rollup.
+#[derive(Debug)]
+pub struct MockChain {
+    /// Namespace ID of the rollup
+    pub ns_id: u32,
+    pub blocks: Vec<MockBlock>,
+}
+
+impl MockChain {
+    /// Simulate `num_blocks` blocks, every third of which doesn't contain the
+    /// rollup's namespace.
+    pub fn new<R: RngCore>(vid_param: &VidParam, ns_id: u32, num_blocks: u64, rng: &mut R) -> Self {
+        let mut vid = vid_scheme(NUM_STORAGE_NODES, vid_param);
+        let mut bmt = BlockMerkleTree::new(32);
+        let blocks = (0..num_blocks)
+            .map(|height| {
+                let ns_payload = if height % 3 == 1 {
+                    vec![]
+                } else {
+                    mock_ns_payload(rng)
+                };
+
+                let (mut header, payload, vid_common, ns_proof) =
+                    mock_block(height, ns_id, &ns_payload, &mut vid, rng);
+                // Each header commits to all blocks before it
+                *header.block_merkle_tree_root_mut() = bmt.commitment();
+                bmt.push(header.commit()).unwrap();
+
+                MockBlock {
+                    header,
+                    payload,
+                    vid_common,
+                    ns_payload,
+                    ns_proof,
+                }
+            })
+            .collect();
+        Self { ns_id, blocks }
+    }
+
+    /// Membership proof of the block at `height` in the block Merkle tree
+    /// containing the first `bmt_height` blocks.
+    pub fn block_merkle_proof(&self, bmt_height: u64, height: u64) -> Option<BlockMerkleTreeProof> {
+        if height >= bmt_height || bmt_height > self.blocks.len() as u64 {
+            return None;
+        }
+        let mut bmt = BlockMerkleTree::new(32);
+        self.blocks[..bmt_height as usize]
+            .iter()
+            .for_each(|block| bmt.push(block.header.commit()).unwrap());
+        bmt.lookup(height).expect_ok().ok().map(|(_, proof)| proof)
+    }
+
+    /// Respond to a query on `path` relative to the API root, or `None` if
+    /// there's no such resource.
+    fn respond(&self, path: &str) -> Option<String> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let block = |height: &str| {
+            height
+                .parse::<usize>()
+                .ok()
+                .and_then(|height| self.blocks.get(height))
+        };
+        let json = match segments.as_slice() {
+            ["availability", "header", height] => serde_json::to_string(&block(height)?.header),
+            ["availability", "payload", height] => {
+                let block = block(height)?;
+                serde_json::to_string(&PayloadQueryData {
+                    height: block.header.height(),
+                    block_hash: block.header.commit(),
+                    hash: block.header.payload_commitment().clone(),
+                    size: block.payload.0.len() as u64,
+                    data: Payload(block.payload.0.clone()),
+                })
+            },
+            ["availability", "vid", "common", height] => {
+                let block = block(height)?;
+                serde_json::to_string(&VidCommonQueryData {
+                    height: block.header.height(),
+                    block_hash: block.header.commit(),
+                    payload_hash: block.header.payload_commitment().clone(),
+                    common: block.vid_common.clone(),
+                })
+            },
+            ["availability", "block", height, "namespace", ns_id] => {
+                let block = block(height)?;
+                let ns_id = ns_id.parse().ok()?;
+                let (proof, transactions) = match &block.ns_proof {
+                    Some(ns_proof) if ns_id == self.ns_id => (
+                        Some(NamespaceProof {
+                            ns_payload: block.ns_payload.clone(),
+                            ns_proof: ns_proof.clone(),
+                        }),
+                        NsPayload(&block.ns_payload)
+                            .txs()
+                            .map(|tx| Transaction {
+                                namespace: ns_id,
+                                payload: tx.to_vec(),
+                            })
+                            .collect(),
+                    ),
+                    _ => (None, vec![]),
+                };
+                serde_json::to_string(&NamespaceProofQueryData {
+                    proof,
+                    transactions,
+                })
+            },
+            ["block-state", bmt_height, height] => serde_json::to_string(
+                &self.block_merkle_proof(bmt_height.parse().ok()?, height.parse().ok()?)?,
+            ),
+            _ => return None,
+        };
+        Some(json.unwrap())
+    }
+}
+
+/// A mock query service serving a [`MockChain`] on a local port, shut down
+/// when dropped.
+pub struct MockQueryService {
+    url: String,
+    server: Arc<tiny_http::Server>,
+}
+
+impl MockQueryService {
+    /// Start serving `chain` in a background thread.
+    pub fn spawn(chain: MockChain) -> Self {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}/v0", server.server_addr().to_ip().unwrap());
+
+        let listener = server.clone();
+        std::thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                let response = match request
+                    .url()
+                    .strip_prefix("/v0")
+                    .and_then(|path| chain.respond(path))
+                {
+                    Some(body) => tiny_http::Response::from_string(body),
+                    None => tiny_http::Response::from_string("not found").with_status_code(404),
+                };
+                // The client may have hung up, nothing to do about it
+                let _ = request.respond(response);
+            }
+        });
+        Self { url, server }
+    }
+
+    /// URL of the API root, to be passed to
+    /// [`crate::client::QueryServiceClient`].
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for MockQueryService {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockChain, MockQueryService};
+    use crate::{client::QueryServiceClient, mock::test_vid_param};
+    use espresso_derivation_utils::{
+        derivation::DerivationError, ns_payload::NsPayload, PrevBatch,
+    };
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_derive_batch_from_mock_query_service() {
+        let mut rng = rand::rngs::StdRng::from_seed([2u8; 32]);
+        let vid_param = test_vid_param(&mut rng);
+        let ns_id = 42;
+        let chain = MockChain::new(&vid_param, ns_id, 8, &mut rng);
+        let expected_txs: Vec<u8> = chain.blocks[2..=5]
+            .iter()
+            .flat_map(|block| block.ns_payload.clone())
+            .collect();
+        let expected_num_txs: u32 = chain.blocks[2..=5]
+            .iter()
+            .map(|block| NsPayload(&block.ns_payload).num_txs())
+            .sum();
+        let service = MockQueryService::spawn(chain);
+        let client = QueryServiceClient::new(service.url());
+
+        // Derive a batch of blocks in the middle of the chain, against the
+        // block Merkle tree committed in the latest header
+        let batch = client.derivation_batch(2..=5, ns_id, 7).unwrap();
+        assert_eq!(batch.rollup_txs.0, expected_txs);
+        let inputs = batch.into_inputs(vid_param, ns_id, None);
+        assert_eq!(inputs.transactions().count(), expected_num_txs as usize);
+        let public_inputs = inputs.verify().unwrap();
+        assert_eq!(public_inputs.ns_id, ns_id);
+        assert_eq!(public_inputs.start_height, 2);
+        assert_eq!(public_inputs.end_height, 5);
+
+        // The next batch continues from this one
+        let prev_batch = PrevBatch {
+            end_height: public_inputs.end_height,
+            accumulator: public_inputs.accumulator,
+        };
+        let vid_param = || test_vid_param(&mut rand::rngs::StdRng::from_seed([2u8; 32]));
+        let inputs = client
+            .derivation_batch(6..=6, ns_id, 7)
+            .unwrap()
+            .into_inputs(vid_param(), ns_id, Some(prev_batch));
+        assert!(inputs.verify().is_ok());
+
+        // Dropping a block from the middle of the batch is caught natively.
+        // The block at height 4 doesn't contain the namespace, so that the
+        // payload ranges stay contiguous without it.
+        let mut inputs = client
+            .derivation_batch(2..=5, ns_id, 7)
+            .unwrap()
+            .into_inputs(vid_param(), ns_id, None);
+        let (range, proof) = inputs.block_derivation_proofs.remove(2);
+        assert_eq!(proof.block_header.height(), 4);
+        assert!(range.is_empty());
+        assert_eq!(
+            inputs.verify().unwrap_err(),
+            DerivationError::HeightGap { prev: 3, height: 5 }
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Named parameter sets for the VID public parameter, i.e. the KZG SRS of the
+//! degree used by each Espresso deployment, so that the prover and the
+//! verifying contract agree on `vid_param_hash`.
+//!
+//! The SRS is loaded, in order of preference:
+//! 1. from the cache, holding the bincode form of [`VidParam`] (uncompressed,
+//!    fast to load) alongside its Sha256 checksum in a `.sha256` file, keyed by
+//!    the profile and the source of the SRS
+//! 2. from a local SRS file, in arkworks compressed form, checked against an
+//!    expected Sha256 checksum and trimmed to the profile's degree
+//! 3. from the Aztec ceremony through `ark-srs`, which Espresso's SRS is
+//!    derived from
+//!
+//! and must match the `vid_param_hash` pinned for the profile, if any.
+
+use crate::mock;
+use anyhow::{bail, Context};
+use ark_serialize::CanonicalDeserialize;
+use espresso_derivation_utils::block::payload::{self, compute_vid_param_hash, VidParam};
+use jf_pcs::prelude::UnivariateUniversalParams;
+use primitive_types::H256;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// A named parameter set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SrsProfile {
+    /// Low degree SRS for the mock inputs, for demo only
+    Demo,
+    /// SRS of Espresso's networks, the same for all of them
+    #[value(alias = "testnet", alias = "mainnet")]
+    Espresso,
+}
+
+impl Display for SrsProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Demo => "demo",
+            Self::Espresso => "espresso",
+        })
+    }
+}
+
+impl SrsProfile {
+    /// Degree of the SRS.
+    pub fn degree(self) -> usize {
+        match self {
+            Self::Demo => mock::SRS_DEGREE,
+            Self::Espresso => payload::SRS_DEGREE,
+        }
+    }
+
+    /// Pinned Sha256 checksum of the SRS file of this profile, if any.
+    pub fn srs_checksum(self) -> Option<H256> {
+        // TODO: pin the checksum of the SRS file published by Espresso
+        None
+    }
+
+    /// Pinned `vid_param_hash` of this profile, if any, which the verifying
+    /// contract is deployed with.
+    pub fn vid_param_hash(self) -> Option<H256> {
+        // TODO: pin the hashes printed by `sp1-prove srs-info` once checked
+        // against the deployed contracts
+        None
+    }
+}
+
+/// Where to load the SRS of a profile from.
+#[derive(Debug, Clone, clap::Args)]
+pub struct SrsArgs {
+    /// Parameter set of the VID public parameter
+    #[clap(long, value_enum, default_value_t = SrsProfile::Demo)]
+    pub srs_profile: SrsProfile,
+
+    /// Load the SRS from this file, in arkworks compressed form, instead of
+    /// the Aztec ceremony
+    #[clap(long)]
+    pub srs_file: Option<PathBuf>,
+
+    /// Expected Sha256 checksum of `srs_file`, overriding the pinned one of
+    /// the profile
+    #[clap(long, requires = "srs_file")]
+    pub srs_checksum: Option<H256>,
+
+    /// Directory to cache the SRS in, skipped if not given
+    #[clap(long, env = "SRS_CACHE_DIR")]
+    pub srs_cache_dir: Option<PathBuf>,
+}
+
+impl SrsArgs {
+    /// Load the VID public parameter of the selected profile.
+    pub fn load(&self) -> anyhow::Result<VidParam> {
+        load_vid_param(
+            self.srs_profile,
+            self.srs_file
+                .as_deref()
+                .map(|path| (path, self.srs_checksum)),
+            self.srs_cache_dir.as_deref(),
+        )
+    }
+}
+
+/// Where an SRS comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SrsSource {
+    /// The Aztec ceremony
+    Aztec,
+    /// A local SRS file with this checksum
+    File(H256),
+}
+
+impl Display for SrsSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aztec => f.write_str("aztec"),
+            Self::File(checksum) => write!(f, "file-{}", hex::encode(checksum)),
+        }
+    }
+}
+
+/// Path of the cached VID public parameter of `profile` loaded from `source`
+/// in `cache_dir`, and of its checksum. Keying the cache by source means an
+/// SRS file with another checksum is never served the cache of a former one.
+fn cache_paths(profile: SrsProfile, source: SrsSource, cache_dir: &Path) -> (PathBuf, PathBuf) {
+    let path = cache_dir.join(format!(
+        "vid-param-{profile}-{}-{source}.bin",
+        profile.degree()
+    ));
+    (path.clone(), path.with_extension("bin.sha256"))
+}
+
+/// Load the VID public parameter of `profile`, see the module documentation.
+///
+/// `srs_file` is the path of a local SRS file, with its expected checksum if
+/// it isn't pinned for the profile.
+pub fn load_vid_param(
+    profile: SrsProfile,
+    srs_file: Option<(&Path, Option<H256>)>,
+    cache_dir: Option<&Path>,
+) -> anyhow::Result<VidParam> {
+    // Resolve the checksum of the SRS file first, as it keys the cache
+    let srs_file = match srs_file {
+        Some((path, checksum)) => match checksum.or(profile.srs_checksum()) {
+            Some(checksum) => Some((path, checksum)),
+            None => bail!("no checksum to check {} against", path.display()),
+        },
+        None => None,
+    };
+    let source = srs_file.map_or(SrsSource::Aztec, |(_, checksum)| SrsSource::File(checksum));
+    if let Some(cache_dir) = cache_dir {
+        match load_cached(profile, source, cache_dir) {
+            Ok(Some(param)) => return Ok(param),
+            Ok(None) => {},
+            Err(err) => tracing::warn!("Ignoring the cached SRS: {err:#}"),
+        }
+    }
+
+    let param = match srs_file {
+        Some((path, checksum)) => load_srs_file(profile, path, checksum)?,
+        None => load_aztec_srs(profile)?,
+    };
+    let vid_param_hash = compute_vid_param_hash(&param);
+    if let Some(expected) = profile.vid_param_hash() {
+        if vid_param_hash != expected {
+            bail!(
+                "vid_param_hash of the {profile} SRS is {vid_param_hash:?}, expecting {expected:?}"
+            );
+        }
+    }
+
+    if let Some(cache_dir) = cache_dir {
+        let (path, checksum_path) = cache_paths(profile, source, cache_dir);
+        let bytes = bincode::serialize(&param).unwrap();
+        std::fs::create_dir_all(cache_dir)
+            .and_then(|_| std::fs::write(&path, &bytes))
+            .and_then(|_| std::fs::write(&checksum_path, hex::encode(Sha256::digest(&bytes))))
+            .with_context(|| format!("failed to cache the SRS in {}", cache_dir.display()))?;
+    }
+    Ok(param)
+}
+
+/// Load the cached VID public parameter of `profile` loaded from `source`,
+/// `None` if absent. The cache is checked against its checksum before
+/// deserializing, then against the pinned `vid_param_hash` of the profile.
+fn load_cached(
+    profile: SrsProfile,
+    source: SrsSource,
+    cache_dir: &Path,
+) -> anyhow::Result<Option<VidParam>> {
+    let (path, checksum_path) = cache_paths(profile, source, cache_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&path)?;
+    let expected: [u8; 32] = hex::decode(std::fs::read_to_string(&checksum_path)?.trim())?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("malformed checksum in {}", checksum_path.display()))?;
+    if Sha256::digest(&bytes).as_slice() != expected {
+        bail!("{} doesn't match its checksum", path.display());
+    }
+    let param: VidParam = bincode::deserialize(&bytes)?;
+    if profile
+        .vid_param_hash()
+        .is_some_and(|pinned| pinned != compute_vid_param_hash(&param))
+    {
+        bail!("{} doesn't match the pinned hash", path.display());
+    }
+    Ok(Some(param))
+}
+
+/// Load an SRS file in arkworks compressed form, check it against the
+/// `expected` checksum, and trim it to the degree of `profile`.
+fn load_srs_file(profile: SrsProfile, path: &Path, expected: H256) -> anyhow::Result<VidParam> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let actual = H256::from_slice(&Sha256::digest(&bytes));
+    if actual != expected {
+        bail!(
+            "checksum of {} is {actual:?}, expecting {expected:?}",
+            path.display()
+        );
+    }
+
+    let mut srs = UnivariateUniversalParams::deserialize_compressed(&*bytes)?;
+    if srs.powers_of_g.len() <= profile.degree() {
+        bail!(
+            "SRS of degree {} is too small for the {profile} profile",
+            srs.powers_of_g.len().saturating_sub(1)
+        );
+    }
+    srs.powers_of_g.truncate(profile.degree() + 1);
+    Ok(VidParam::new(srs))
+}
+
+/// Load the SRS of `profile` from the Aztec ceremony.
+fn load_aztec_srs(profile: SrsProfile) -> anyhow::Result<VidParam> {
+    let srs = ark_srs::kzg10::aztec20::setup(profile.degree())
+        .map_err(|err| anyhow::anyhow!("Aztec SRS failed to load: {err:?}"))?;
+    Ok(VidParam::new(UnivariateUniversalParams {
+        powers_of_g: srs.powers_of_g,
+        h: srs.h,
+        beta_h: srs.beta_h,
+        powers_of_h: vec![srs.h, srs.beta_h],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_paths, load_cached, load_vid_param, SrsProfile, SrsSource};
+    use crate::mock::test_vid_param;
+    use espresso_derivation_utils::block::payload::compute_vid_param_hash;
+    use primitive_types::H256;
+    use rand::SeedableRng;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_srs_cache() {
+        let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+        let param = test_vid_param(&mut rng);
+        let vid_param_hash = compute_vid_param_hash(&param);
+        let cache_dir = std::env::temp_dir().join("espresso-derivation-srs-test");
+        let source = SrsSource::File(H256::repeat_byte(1));
+        let (path, checksum_path) = cache_paths(SrsProfile::Demo, source, &cache_dir);
+        let bytes = bincode::serialize(&param).unwrap();
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        std::fs::write(&checksum_path, hex::encode(Sha256::digest(&bytes))).unwrap();
+
+        let cached = load_cached(SrsProfile::Demo, source, &cache_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(compute_vid_param_hash(&cached), vid_param_hash);
+        assert!(load_cached(SrsProfile::Espresso, source, &cache_dir)
+            .unwrap()
+            .is_none());
+        // Nor is it served for another source
+        assert!(load_cached(SrsProfile::Demo, SrsSource::Aztec, &cache_dir)
+            .unwrap()
+            .is_none());
+        let other_file = SrsSource::File(H256::repeat_byte(2));
+        assert!(load_cached(SrsProfile::Demo, other_file, &cache_dir)
+            .unwrap()
+            .is_none());
+
+        // A corrupted cache is rejected
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+        assert!(load_cached(SrsProfile::Demo, source, &cache_dir).is_err());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    /// Recompute the `vid_param_hash` of `profile` from the Aztec ceremony and
+    /// check it against the pinned one.
+    fn check_pinned_vid_param_hash(profile: SrsProfile) {
+        let param = load_vid_param(profile, None, None).unwrap();
+        assert_eq!(
+            profile.vid_param_hash(),
+            Some(compute_vid_param_hash(&param)),
+            "pin the vid_param_hash of the {profile} profile"
+        );
+    }
+
+    #[test]
+    #[ignore = "downloads the Aztec ceremony"]
+    fn test_demo_vid_param_hash() {
+        check_pinned_vid_param_hash(SrsProfile::Demo);
+    }
+
+    #[test]
+    #[ignore = "downloads the Aztec ceremony, and takes minutes to hash the SRS"]
+    fn test_espresso_vid_param_hash() {
+        check_pinned_vid_param_hash(SrsProfile::Espresso);
+    }
+}
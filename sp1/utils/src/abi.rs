@@ -0,0 +1,631 @@
+//! Solidity ABI layout of the public values committed by the derivation,
+//! aggregation and consistency programs, shared by the `EspressoDerivation`
+//! contract and off-chain tooling.
+//!
+//! In every mode, the public values of the derivation program start with the
+//! discriminator of the mode as a `uint8` word, see [`DerivationMode::id`].
+
+use crate::{
+    blob::BlobPublicInputs,
+    commitment::CommitmentSchemeId,
+    consistency::ConsistencyPublicInputs,
+    finality::{FinalityPublicInputs, StakeTableCommitment},
+    inclusion::NonInclusionPublicInputs,
+    light_client::{LightClientPublicInputs, LightClientState},
+    DerivationMode, PublicInputs,
+};
+use alloy_primitives::{B256, U256};
+use alloy_sol_types::{sol, sol_data, SolType};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use jf_merkle_tree::MerkleCommitment;
+
+sol! {
+    /// Public values of a derivation proof, mirroring [`PublicInputs`].
+    #[sol(all_derives)]
+    struct PublicValuesStruct {
+        /// `CommitmentSchemeId` as its index: 0 for SHA-256, 1 for
+        /// Keccak-256, 2 for Poseidon over BN254, 3 for EIP-4844 blobs
+        uint8 rollupCommitScheme;
+        bytes32 rollupTxsCommit;
+        bytes32 vidParamHash;
+        uint32 nsId;
+        /// Root digest of the block Merkle tree
+        bytes32 bmtDigest;
+        /// Height of the block Merkle tree
+        uint64 bmtHeight;
+        /// Number of blocks in the block Merkle tree
+        uint64 bmtNumLeaves;
+        uint64 startHeight;
+        uint64 endHeight;
+        /// Whether the batch is chained, in which case `prevEndHeight` and
+        /// `prevAccumulator` are those of the previous batch, or zero
+        /// otherwise
+        bool chained;
+        uint64 prevEndHeight;
+        bytes32 prevAccumulator;
+        bytes32 accumulator;
+    }
+
+    /// Public values of an aggregated derivation proof.
+    #[sol(all_derives)]
+    struct AggregatedPublicValuesStruct {
+        /// Digest of the verifying key of the derivation program, the words
+        /// of `hash_u32()` in big-endian
+        bytes32 derivationVkey;
+        PublicValuesStruct derivation;
+    }
+
+    /// Public values of a derivation proof in non-inclusion mode, mirroring
+    /// [`NonInclusionPublicInputs`].
+    #[sol(all_derives)]
+    struct NonInclusionPublicValuesStruct {
+        PublicValuesStruct derivation;
+        bytes32 txHash;
+        bool notIncluded;
+    }
+
+    /// Public values of a derivation proof in blob mode, mirroring
+    /// [`BlobPublicInputs`].
+    #[sol(all_derives)]
+    struct BlobPublicValuesStruct {
+        /// `rollupTxsCommit` is the versioned hash of the blob
+        PublicValuesStruct derivation;
+        bytes32 setupHash;
+    }
+
+    /// State of the Espresso light client contract, mirroring
+    /// [`LightClientState`].
+    #[sol(all_derives)]
+    struct LightClientStateStruct {
+        uint64 viewNum;
+        uint64 blockHeight;
+        /// BN254 scalar field element, as stored by the light client contract
+        uint256 blockCommRoot;
+    }
+
+    /// Public values of a derivation proof in light client mode, mirroring
+    /// [`LightClientPublicInputs`].
+    #[sol(all_derives)]
+    struct LightClientPublicValuesStruct {
+        LightClientStateStruct lightClientState;
+        PublicValuesStruct derivation;
+    }
+
+    /// Commitment of the Espresso stake table, mirroring
+    /// [`StakeTableCommitment`]. Each part is a BN254 scalar field element, as
+    /// stored by the light client contract.
+    #[sol(all_derives)]
+    struct StakeTableCommitmentStruct {
+        uint256 blsKeyComm;
+        uint256 schnorrKeyComm;
+        uint256 amountComm;
+    }
+
+    /// Public values of a derivation proof in finality mode, mirroring
+    /// [`FinalityPublicInputs`].
+    #[sol(all_derives)]
+    struct FinalityPublicValuesStruct {
+        StakeTableCommitmentStruct stakeTableComm;
+        uint64 viewNum;
+        uint64 blockHeight;
+        PublicValuesStruct derivation;
+    }
+
+    /// Public values of the consistency program, mirroring
+    /// [`ConsistencyPublicInputs`]. Verifying keys are encoded as in
+    /// [`AggregatedPublicValuesStruct`].
+    #[sol(all_derives)]
+    struct ConsistencyPublicValuesStruct {
+        bytes32 derivationVkey;
+        bytes32 vmVkey;
+        bytes32 lightClientVkey;
+        LightClientStateStruct lightClientState;
+        PublicValuesStruct derivation;
+        bytes32 prevStateRoot;
+        bytes32 newStateRoot;
+    }
+}
+
+impl CommitmentSchemeId {
+    /// Index of the scheme in the ABI layout.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::Sha256 => 0,
+            Self::Keccak256 => 1,
+            Self::PoseidonBn254 => 2,
+            Self::Eip4844Blob => 3,
+        }
+    }
+}
+
+impl From<&PublicInputs> for PublicValuesStruct {
+    fn from(inputs: &PublicInputs) -> Self {
+        let mut bmt_digest = Vec::new();
+        inputs
+            .bmt_commitment
+            .digest()
+            .serialize_compressed(&mut bmt_digest)
+            .unwrap();
+        Self {
+            rollupCommitScheme: inputs.rollup_commit_scheme.to_u8(),
+            rollupTxsCommit: B256::from(inputs.rollup_txs_commit.0),
+            vidParamHash: B256::from(inputs.vid_param_hash.0),
+            nsId: inputs.ns_id,
+            bmtDigest: B256::from_slice(&bmt_digest),
+            bmtHeight: inputs.bmt_commitment.height() as u64,
+            bmtNumLeaves: inputs.bmt_commitment.size(),
+            startHeight: inputs.start_height,
+            endHeight: inputs.end_height,
+            chained: inputs.prev_batch.is_some(),
+            prevEndHeight: inputs
+                .prev_batch
+                .map_or(0, |prev_batch| prev_batch.end_height),
+            prevAccumulator: inputs.prev_batch.map_or(B256::ZERO, |prev_batch| {
+                B256::from(prev_batch.accumulator.0)
+            }),
+            accumulator: B256::from(inputs.accumulator.0),
+        }
+    }
+}
+
+impl From<&NonInclusionPublicInputs> for NonInclusionPublicValuesStruct {
+    fn from(inputs: &NonInclusionPublicInputs) -> Self {
+        Self {
+            derivation: PublicValuesStruct::from(&inputs.derivation),
+            txHash: B256::from(inputs.tx_hash.0),
+            notIncluded: inputs.not_included,
+        }
+    }
+}
+
+impl From<&BlobPublicInputs> for BlobPublicValuesStruct {
+    fn from(inputs: &BlobPublicInputs) -> Self {
+        Self {
+            derivation: PublicValuesStruct::from(&inputs.derivation),
+            setupHash: B256::from(inputs.setup_hash.0),
+        }
+    }
+}
+
+impl From<&LightClientState> for LightClientStateStruct {
+    fn from(state: &LightClientState) -> Self {
+        Self {
+            viewNum: state.view_num,
+            blockHeight: state.block_height,
+            blockCommRoot: U256::from_limbs(state.block_comm_root.into_bigint().0),
+        }
+    }
+}
+
+impl From<&LightClientPublicInputs> for LightClientPublicValuesStruct {
+    fn from(inputs: &LightClientPublicInputs) -> Self {
+        Self {
+            lightClientState: LightClientStateStruct::from(&inputs.light_client_state),
+            derivation: PublicValuesStruct::from(&inputs.derivation),
+        }
+    }
+}
+
+impl From<&StakeTableCommitment> for StakeTableCommitmentStruct {
+    fn from(comm: &StakeTableCommitment) -> Self {
+        Self {
+            blsKeyComm: U256::from_limbs(comm.bls_key_comm.into_bigint().0),
+            schnorrKeyComm: U256::from_limbs(comm.schnorr_key_comm.into_bigint().0),
+            amountComm: U256::from_limbs(comm.amount_comm.into_bigint().0),
+        }
+    }
+}
+
+impl From<&FinalityPublicInputs> for FinalityPublicValuesStruct {
+    fn from(inputs: &FinalityPublicInputs) -> Self {
+        Self {
+            stakeTableComm: StakeTableCommitmentStruct::from(&inputs.stake_table_comm),
+            viewNum: inputs.view_number,
+            blockHeight: inputs.block_height,
+            derivation: PublicValuesStruct::from(&inputs.derivation),
+        }
+    }
+}
+
+impl From<&ConsistencyPublicInputs> for ConsistencyPublicValuesStruct {
+    fn from(inputs: &ConsistencyPublicInputs) -> Self {
+        Self {
+            derivationVkey: vkey_bytes(&inputs.derivation_vkey),
+            vmVkey: vkey_bytes(&inputs.vm_vkey),
+            lightClientVkey: vkey_bytes(&inputs.light_client_vkey),
+            lightClientState: LightClientStateStruct::from(&inputs.light_client_state),
+            derivation: inputs.derivation.clone(),
+            prevStateRoot: B256::from(inputs.prev_state_root.0),
+            newStateRoot: B256::from(inputs.new_state_root.0),
+        }
+    }
+}
+
+/// Digest of a verifying key as the words of `hash_u32()` in big-endian.
+fn vkey_bytes(vkey: &[u32; 8]) -> B256 {
+    let mut bytes = [0u8; 32];
+    bytes
+        .chunks_mut(4)
+        .zip(vkey)
+        .for_each(|(bytes, word)| bytes.copy_from_slice(&word.to_be_bytes()));
+    B256::from(bytes)
+}
+
+/// ABI-encode the discriminator of `mode`, the first word of the public values
+/// of the derivation program.
+pub fn encode_mode(mode: &DerivationMode) -> Vec<u8> {
+    sol_data::Uint::<8>::abi_encode(&mode.id())
+}
+
+/// Split the public values of the derivation program into the discriminator of
+/// its mode and the public values of that mode.
+pub fn decode_mode(bytes: &[u8]) -> Result<(u8, &[u8]), alloy_sol_types::Error> {
+    let (word, rest) = bytes.split_at(std::cmp::min(bytes.len(), 32));
+    Ok((sol_data::Uint::<8>::abi_decode(word, true)?, rest))
+}
+
+/// ABI-encode the public values of a derivation proof, as
+/// `(uint8, PublicValuesStruct)` with the discriminator of
+/// [`DerivationMode::Derivation`].
+pub fn encode_public_values(inputs: &PublicInputs) -> Vec<u8> {
+    let mut bytes = encode_mode(&DerivationMode::Derivation);
+    bytes.extend(PublicValuesStruct::abi_encode(&PublicValuesStruct::from(
+        inputs,
+    )));
+    bytes
+}
+
+/// Decode the public values committed by the derivation program, which must be
+/// in [`DerivationMode::Derivation`].
+pub fn decode_public_values(bytes: &[u8]) -> Result<PublicValuesStruct, alloy_sol_types::Error> {
+    PublicValuesStruct::abi_decode(expect_mode(bytes, DerivationMode::DERIVATION_ID)?, true)
+}
+
+/// ABI-encode the public values of a derivation proof in non-inclusion mode, as
+/// `(uint8, NonInclusionPublicValuesStruct)` with the discriminator of
+/// [`DerivationMode::NonInclusion`].
+pub fn encode_non_inclusion_public_values(inputs: &NonInclusionPublicInputs) -> Vec<u8> {
+    let mut bytes = sol_data::Uint::<8>::abi_encode(&DerivationMode::NON_INCLUSION_ID);
+    bytes.extend(NonInclusionPublicValuesStruct::abi_encode(
+        &NonInclusionPublicValuesStruct::from(inputs),
+    ));
+    bytes
+}
+
+/// Decode the public values committed by the derivation program, which must be
+/// in [`DerivationMode::NonInclusion`].
+pub fn decode_non_inclusion_public_values(
+    bytes: &[u8],
+) -> Result<NonInclusionPublicValuesStruct, alloy_sol_types::Error> {
+    NonInclusionPublicValuesStruct::abi_decode(
+        expect_mode(bytes, DerivationMode::NON_INCLUSION_ID)?,
+        true,
+    )
+}
+
+/// ABI-encode the public values of a derivation proof in blob mode, as
+/// `(uint8, BlobPublicValuesStruct)` with the discriminator of
+/// [`DerivationMode::Blob`].
+pub fn encode_blob_public_values(inputs: &BlobPublicInputs) -> Vec<u8> {
+    let mut bytes = encode_mode(&DerivationMode::Blob);
+    bytes.extend(BlobPublicValuesStruct::abi_encode(
+        &BlobPublicValuesStruct::from(inputs),
+    ));
+    bytes
+}
+
+/// Decode the public values committed by the derivation program, which must be
+/// in [`DerivationMode::Blob`].
+pub fn decode_blob_public_values(
+    bytes: &[u8],
+) -> Result<BlobPublicValuesStruct, alloy_sol_types::Error> {
+    BlobPublicValuesStruct::abi_decode(expect_mode(bytes, DerivationMode::BLOB_ID)?, true)
+}
+
+/// ABI-encode the public values of a derivation proof in light client mode, as
+/// `(uint8, LightClientPublicValuesStruct)` with the discriminator of
+/// [`DerivationMode::LightClient`].
+pub fn encode_light_client_public_values(inputs: &LightClientPublicInputs) -> Vec<u8> {
+    let mut bytes = sol_data::Uint::<8>::abi_encode(&DerivationMode::LIGHT_CLIENT_ID);
+    bytes.extend(LightClientPublicValuesStruct::abi_encode(
+        &LightClientPublicValuesStruct::from(inputs),
+    ));
+    bytes
+}
+
+/// Decode the public values committed by the derivation program, which must be
+/// in [`DerivationMode::LightClient`].
+pub fn decode_light_client_public_values(
+    bytes: &[u8],
+) -> Result<LightClientPublicValuesStruct, alloy_sol_types::Error> {
+    LightClientPublicValuesStruct::abi_decode(
+        expect_mode(bytes, DerivationMode::LIGHT_CLIENT_ID)?,
+        true,
+    )
+}
+
+/// ABI-encode the public values of a derivation proof in finality mode, as
+/// `(uint8, FinalityPublicValuesStruct)` with the discriminator of
+/// [`DerivationMode::Finality`].
+pub fn encode_finality_public_values(inputs: &FinalityPublicInputs) -> Vec<u8> {
+    let mut bytes = sol_data::Uint::<8>::abi_encode(&DerivationMode::FINALITY_ID);
+    bytes.extend(FinalityPublicValuesStruct::abi_encode(
+        &FinalityPublicValuesStruct::from(inputs),
+    ));
+    bytes
+}
+
+/// Decode the public values committed by the derivation program, which must be
+/// in [`DerivationMode::Finality`].
+pub fn decode_finality_public_values(
+    bytes: &[u8],
+) -> Result<FinalityPublicValuesStruct, alloy_sol_types::Error> {
+    FinalityPublicValuesStruct::abi_decode(expect_mode(bytes, DerivationMode::FINALITY_ID)?, true)
+}
+
+/// The public values of the mode `mode_id` following its discriminator.
+fn expect_mode(bytes: &[u8], mode_id: u8) -> Result<&[u8], alloy_sol_types::Error> {
+    let (mode, bytes) = decode_mode(bytes)?;
+    if mode != mode_id {
+        return Err(alloy_sol_types::Error::custom(format!(
+            "public values of the derivation mode {mode}, not {mode_id}"
+        )));
+    }
+    Ok(bytes)
+}
+
+/// ABI-encode the public values of an aggregated derivation proof.
+pub fn encode_aggregated_public_values(
+    derivation_vkey: &[u32; 8],
+    derivation: PublicValuesStruct,
+) -> Vec<u8> {
+    AggregatedPublicValuesStruct::abi_encode(&AggregatedPublicValuesStruct {
+        derivationVkey: vkey_bytes(derivation_vkey),
+        derivation,
+    })
+}
+
+/// Decode the public values committed by the aggregation program.
+pub fn decode_aggregated_public_values(
+    bytes: &[u8],
+) -> Result<AggregatedPublicValuesStruct, alloy_sol_types::Error> {
+    AggregatedPublicValuesStruct::abi_decode(bytes, true)
+}
+
+/// ABI-encode the public values of the consistency program.
+pub fn encode_consistency_public_values(inputs: &ConsistencyPublicInputs) -> Vec<u8> {
+    ConsistencyPublicValuesStruct::abi_encode(&ConsistencyPublicValuesStruct::from(inputs))
+}
+
+/// Decode the public values committed by the consistency program.
+pub fn decode_consistency_public_values(
+    bytes: &[u8],
+) -> Result<ConsistencyPublicValuesStruct, alloy_sol_types::Error> {
+    ConsistencyPublicValuesStruct::abi_decode(bytes, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_aggregated_public_values, decode_blob_public_values, decode_finality_public_values,
+        decode_light_client_public_values, decode_mode, decode_non_inclusion_public_values,
+        decode_public_values, encode_aggregated_public_values, encode_blob_public_values,
+        encode_finality_public_values, encode_light_client_public_values, encode_mode,
+        encode_non_inclusion_public_values, encode_public_values, LightClientStateStruct,
+        NonInclusionPublicValuesStruct, PublicValuesStruct, StakeTableCommitmentStruct,
+    };
+    use crate::{
+        blob::BlobPublicInputs,
+        block::header::BlockMerkleTree,
+        commitment::CommitmentSchemeId,
+        finality::{FinalityPublicInputs, StakeTableCommitment},
+        inclusion::NonInclusionPublicInputs,
+        light_client::{LightClientPublicInputs, LightClientState},
+        DerivationMode, PrevBatch, PublicInputs,
+    };
+    use alloy_primitives::{B256, U256};
+    use alloy_sol_types::SolType;
+    use ark_bn254::Fr;
+    use jf_merkle_tree::MerkleTreeScheme;
+    use primitive_types::H256;
+
+    #[test]
+    fn test_public_values_abi_round_trip() {
+        let inputs = PublicInputs {
+            rollup_commit_scheme: CommitmentSchemeId::Keccak256,
+            rollup_txs_commit: H256::repeat_byte(1),
+            vid_param_hash: H256::repeat_byte(2),
+            ns_id: 42,
+            bmt_commitment: BlockMerkleTree::new(32).commitment(),
+            start_height: 10,
+            end_height: 12,
+            prev_batch: Some(PrevBatch {
+                end_height: 9,
+                accumulator: H256::repeat_byte(3),
+            }),
+            accumulator: H256::repeat_byte(4),
+        };
+        let bytes = encode_public_values(&inputs);
+        // The mode, then 13 static words
+        assert_eq!(bytes.len(), 14 * 32);
+        assert_eq!(
+            decode_mode(&bytes).unwrap().0,
+            DerivationMode::DERIVATION_ID
+        );
+        let values = decode_public_values(&bytes).unwrap();
+        assert_eq!(values, PublicValuesStruct::from(&inputs));
+        assert_eq!(values.rollupCommitScheme, 1);
+        assert_eq!(values.nsId, 42);
+        assert!(values.chained);
+        assert_eq!(values.prevEndHeight, 9);
+        assert_eq!(values.accumulator.0, [4u8; 32]);
+
+        let bytes = encode_aggregated_public_values(&[1, 2, 3, 4, 5, 6, 7, 8], values.clone());
+        let aggregated = decode_aggregated_public_values(&bytes).unwrap();
+        assert_eq!(aggregated.derivation, values);
+        assert_eq!(aggregated.derivationVkey[..8], [0, 0, 0, 1, 0, 0, 0, 2]);
+
+        assert!(decode_public_values(&bytes[..32]).is_err());
+
+        // The public values of another mode are rejected
+        let mut bytes = encode_public_values(&inputs);
+        bytes[..32].copy_from_slice(&encode_mode(&DerivationMode::Blob));
+        assert_eq!(decode_mode(&bytes).unwrap().0, DerivationMode::BLOB_ID);
+        assert!(decode_public_values(&bytes).is_err());
+        assert!(decode_mode(&[0u8; 31]).is_err());
+
+        // In non-inclusion mode, the transaction follows the public values
+        let non_inclusion = NonInclusionPublicInputs {
+            derivation: inputs,
+            tx_hash: H256::repeat_byte(6),
+            not_included: true,
+        };
+        let bytes = encode_non_inclusion_public_values(&non_inclusion);
+        // The mode, then 15 static words
+        assert_eq!(bytes.len(), 16 * 32);
+        let values = decode_non_inclusion_public_values(&bytes).unwrap();
+        assert_eq!(
+            values.derivation,
+            PublicValuesStruct::from(&non_inclusion.derivation)
+        );
+        assert_eq!(values.txHash.0, [6u8; 32]);
+        assert!(values.notIncluded);
+        assert!(decode_public_values(&bytes).is_err());
+        let inputs = non_inclusion.derivation;
+
+        // In blob mode, the setup hash follows the public values
+        let blob = BlobPublicInputs {
+            derivation: inputs,
+            setup_hash: H256::repeat_byte(7),
+        };
+        let bytes = encode_blob_public_values(&blob);
+        assert_eq!(bytes.len(), 15 * 32);
+        let values = decode_blob_public_values(&bytes).unwrap();
+        assert_eq!(
+            values.derivation,
+            PublicValuesStruct::from(&blob.derivation)
+        );
+        assert_eq!(values.setupHash.0, [7u8; 32]);
+        assert!(decode_non_inclusion_public_values(&bytes).is_err());
+        let inputs = blob.derivation;
+
+        // In finality mode, the public values follow the stake table and the
+        // finalized block
+        let stake_table_comm = StakeTableCommitment {
+            bls_key_comm: Fr::from(1u64),
+            schnorr_key_comm: Fr::from(2u64),
+            amount_comm: Fr::from(3u64),
+        };
+        let finality = FinalityPublicInputs {
+            stake_table_comm,
+            view_number: 8,
+            block_height: 13,
+            derivation: inputs,
+        };
+        let bytes = encode_finality_public_values(&finality);
+        // The mode, the 3 words of the stake table, then 15 static words
+        assert_eq!(bytes.len(), 19 * 32);
+        let values = decode_finality_public_values(&bytes).unwrap();
+        assert_eq!(
+            values.stakeTableComm,
+            StakeTableCommitmentStruct::from(&stake_table_comm)
+        );
+        assert_eq!(values.stakeTableComm.amountComm, U256::from(3));
+        assert_eq!(values.viewNum, 8);
+        assert_eq!(values.blockHeight, 13);
+        assert_eq!(
+            values.derivation,
+            PublicValuesStruct::from(&finality.derivation)
+        );
+        assert!(decode_light_client_public_values(&bytes).is_err());
+        let inputs = finality.derivation;
+
+        // In light client mode, the public values follow the state
+        let state = LightClientState::new(7, 0, &inputs.bmt_commitment);
+        let bytes = encode_light_client_public_values(&LightClientPublicInputs {
+            light_client_state: state,
+            derivation: inputs,
+        });
+        let values = decode_light_client_public_values(&bytes).unwrap();
+        assert_eq!(
+            values.lightClientState,
+            LightClientStateStruct::from(&state)
+        );
+        assert_eq!(values.derivation.nsId, 42);
+        assert!(decode_public_values(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_public_values_vector() {
+        // Also decoded by `EspressoDerivation.t.sol`
+        let bytes = hex::decode(
+            include_str!("../../contracts/src/fixtures/public_values.hex").trim_start_matches("0x"),
+        )
+        .unwrap();
+        let values = PublicValuesStruct {
+            rollupCommitScheme: 1,
+            rollupTxsCommit: B256::repeat_byte(1),
+            vidParamHash: B256::repeat_byte(2),
+            nsId: 42,
+            bmtDigest: B256::repeat_byte(5),
+            bmtHeight: 32,
+            bmtNumLeaves: 13,
+            startHeight: 10,
+            endHeight: 12,
+            chained: true,
+            prevEndHeight: 9,
+            prevAccumulator: B256::repeat_byte(3),
+            accumulator: B256::repeat_byte(4),
+        };
+        assert_eq!(decode_public_values(&bytes).unwrap(), values);
+        assert_eq!(
+            bytes,
+            [
+                encode_mode(&DerivationMode::Derivation),
+                PublicValuesStruct::abi_encode(&values)
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_non_inclusion_public_values_vector() {
+        // Also decoded by `EspressoDerivation.t.sol`
+        let bytes = hex::decode(
+            include_str!("../../contracts/src/fixtures/non_inclusion_public_values.hex")
+                .trim_start_matches("0x"),
+        )
+        .unwrap();
+        let values = NonInclusionPublicValuesStruct {
+            derivation: PublicValuesStruct {
+                rollupCommitScheme: 1,
+                rollupTxsCommit: B256::repeat_byte(1),
+                vidParamHash: B256::repeat_byte(2),
+                nsId: 42,
+                bmtDigest: B256::repeat_byte(5),
+                bmtHeight: 32,
+                bmtNumLeaves: 13,
+                startHeight: 10,
+                endHeight: 12,
+                chained: true,
+                prevEndHeight: 9,
+                prevAccumulator: B256::repeat_byte(3),
+                accumulator: B256::repeat_byte(4),
+            },
+            txHash: B256::repeat_byte(6),
+            notIncluded: true,
+        };
+        assert_eq!(decode_non_inclusion_public_values(&bytes).unwrap(), values);
+        assert_eq!(
+            bytes,
+            [
+                encode_mode(&DerivationMode::NonInclusion {
+                    tx_hash: H256::repeat_byte(6)
+                }),
+                NonInclusionPublicValuesStruct::abi_encode(&values)
+            ]
+            .concat()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! EIP-4844 blob commitments to the rollup transactions, so that an L1
+//! contract can check a derivation proof against the blob it received.
+//!
+//! Blobs are committed with KZG over BLS12-381, using the setup of the
+//! Ethereum KZG ceremony. This is unrelated to the BN254 KZG of the VID
+//! scheme, thus the setup is a separate input whose hash is public.
+
+use crate::{
+    block::{
+        header::BlockMerkleCommitment,
+        payload::{Payload, VidParam},
+        RollupCommitment,
+    },
+    commitment::CommitmentSchemeId,
+    derivation::{verify_derivation_with_commitment, DerivationError},
+    BlockDerivationProof, PrevBatch, PublicInputs,
+};
+use ark_bls12_381::{Fr, G1Affine, G1Projective};
+use ark_ec::VariableBaseMSM;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use primitive_types::H256;
+use serde::{de::Error as _, ser::Error as _, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// Number of field elements in a blob.
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+/// Number of payload bytes packed into a field element: the most significant
+/// byte of each 32-byte element is left zero to stay below the modulus.
+pub const BYTES_PER_FIELD_ELEMENT: usize = 31;
+/// Maximum byte length of a payload fitting in a blob.
+pub const MAX_BLOB_PAYLOAD_BYTES: usize = FIELD_ELEMENTS_PER_BLOB * BYTES_PER_FIELD_ELEMENT;
+/// Version byte of a KZG versioned hash.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// The G1 points of the Ethereum KZG ceremony in Lagrange form, as needed to
+/// commit to a blob in evaluation form.
+///
+/// Points are stored in bit-reversed order, matching the order of the
+/// evaluations in a blob.
+/// Manual (de)serialization to avoid the expensive validity check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobSetup(pub Vec<G1Affine>);
+
+impl BlobSetup {
+    /// Parse the `trusted_setup.txt` file of the reference implementation
+    /// `c-kzg-4844`, where G1 points in Lagrange form are listed in natural
+    /// order, one compressed point in hex per line, after the number of G1
+    /// and G2 points.
+    pub fn from_trusted_setup_txt(txt: &str) -> Result<Self, String> {
+        let mut lines = txt.lines().map(str::trim);
+        let num_g1: usize = lines
+            .next()
+            .and_then(|line| line.parse().ok())
+            .ok_or("Missing number of G1 points")?;
+        if num_g1 != FIELD_ELEMENTS_PER_BLOB {
+            return Err(format!(
+                "Expected {FIELD_ELEMENTS_PER_BLOB} G1 points, found {num_g1}"
+            ));
+        }
+        // Number of G2 points, unused
+        lines.next().ok_or("Missing number of G2 points")?;
+
+        let points = lines
+            .take(num_g1)
+            .map(|line| {
+                let bytes = hex::decode(line).map_err(|e| format!("{e}"))?;
+                G1Affine::deserialize_compressed(&*bytes).map_err(|e| format!("{e:?}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if points.len() != num_g1 {
+            return Err(format!("Expected {num_g1} G1 points, found {}", points.len()));
+        }
+        Ok(Self::from_natural_order(points))
+    }
+
+    /// Build the setup from the G1 points in Lagrange form in natural order,
+    /// whose number must be a power of two.
+    pub fn from_natural_order(points: Vec<G1Affine>) -> Self {
+        let bits = points.len().trailing_zeros();
+        let mut reversed = points.clone();
+        points.into_iter().enumerate().for_each(|(i, point)| {
+            reversed[i.reverse_bits().checked_shr(usize::BITS - bits).unwrap_or(0)] = point;
+        });
+        Self(reversed)
+    }
+
+    /// Hash of the setup, `Sha256` of all its compressed points in the stored
+    /// order.
+    pub fn hash(&self) -> H256 {
+        let mut hasher = Sha256::new();
+        self.0.iter().for_each(|point| {
+            let mut bytes = Vec::new();
+            point.serialize_compressed(&mut bytes).unwrap();
+            hasher.update(bytes);
+        });
+        let bytes: [u8; 32] = hasher.finalize().into();
+        bytes.into()
+    }
+}
+
+impl Serialize for BlobSetup {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut bytes = Vec::new();
+        self.0
+            .serialize_uncompressed(&mut bytes)
+            .map_err(|e| S::Error::custom(format!("{e:?}")))?;
+        Serialize::serialize(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlobSetup {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let bytes = <Vec<u8> as Deserialize>::deserialize(deserializer)?;
+        <Vec<G1Affine> as CanonicalDeserialize>::deserialize_uncompressed_unchecked(&*bytes)
+            .map_err(|e| D::Error::custom(format!("{e:?}")))
+            .map(BlobSetup)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Public inputs of the derivation program in blob mode, committed ABI-encoded,
+/// see [`crate::abi::BlobPublicValuesStruct`]
+pub struct BlobPublicInputs {
+    /// Public inputs of the derivation relation, where `rollup_txs_commit` is
+    /// the versioned hash of the blob holding the rollup transactions
+    pub derivation: PublicInputs,
+    /// Hash of the used setup, see [`BlobSetup::hash`]
+    pub setup_hash: H256,
+}
+
+/// Encode `payload` as the field elements of a blob, 31 bytes per element
+/// in big-endian, zero-padded to [`FIELD_ELEMENTS_PER_BLOB`] elements.
+/// Return `None` if the payload doesn't fit in a blob.
+pub fn blob_field_elements(payload: &[u8]) -> Option<Vec<Fr>> {
+    if payload.len() > MAX_BLOB_PAYLOAD_BYTES {
+        return None;
+    }
+    let mut elems: Vec<Fr> = payload
+        .chunks(BYTES_PER_FIELD_ELEMENT)
+        .map(|chunk| {
+            let mut bytes = [0u8; 32];
+            bytes[1..1 + chunk.len()].copy_from_slice(chunk);
+            Fr::from_be_bytes_mod_order(&bytes)
+        })
+        .collect();
+    elems.resize(FIELD_ELEMENTS_PER_BLOB, Fr::from(0u64));
+    Some(elems)
+}
+
+/// KZG commitment to the blob holding `payload`, or `None` if the payload
+/// doesn't fit in a blob.
+pub fn blob_commitment(payload: &[u8], setup: &BlobSetup) -> Option<G1Affine> {
+    let elems = blob_field_elements(payload)?;
+    Some(G1Projective::msm(&setup.0, &elems).unwrap().into())
+}
+
+/// Versioned hash of a blob commitment, as exposed to the EVM by the
+/// `BLOBHASH` opcode.
+pub fn versioned_hash(commitment: &G1Affine) -> H256 {
+    let mut bytes = Vec::new();
+    commitment.serialize_compressed(&mut bytes).unwrap();
+    let mut hash: [u8; 32] = Sha256::digest(bytes).into();
+    hash[0] = VERSIONED_HASH_VERSION_KZG;
+    hash.into()
+}
+
+/// Versioned hash of the blob holding `payload`, or `None` if the payload
+/// doesn't fit in a blob.
+pub fn blob_versioned_hash(payload: &[u8], setup: &BlobSetup) -> Option<RollupCommitment> {
+    blob_commitment(payload, setup).map(|commitment| versioned_hash(&commitment))
+}
+
+/// Verifies the derivation relation natively with the versioned hash of the
+/// blob holding `rollup_txs` as the rollup commitment, returning the public
+/// inputs exactly as committed by the derivation program in blob mode.
+pub fn verify_blob_derivation(
+    rollup_txs: &Payload,
+    setup: &BlobSetup,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<BlobPublicInputs, DerivationError> {
+    let rollup_txs_commit =
+        blob_versioned_hash(&rollup_txs.0, setup).ok_or(DerivationError::BlobTooLarge {
+            len: rollup_txs.0.len(),
+            max: MAX_BLOB_PAYLOAD_BYTES,
+        })?;
+    let derivation = verify_derivation_with_commitment(
+        CommitmentSchemeId::Eip4844Blob,
+        rollup_txs_commit,
+        rollup_txs,
+        vid_param,
+        ns_id,
+        bmt_commitment,
+        prev_batch,
+        block_derivation_proofs,
+    )?;
+    Ok(BlobPublicInputs {
+        derivation,
+        setup_hash: setup.hash(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        blob_field_elements, blob_versioned_hash, BlobSetup, FIELD_ELEMENTS_PER_BLOB,
+        MAX_BLOB_PAYLOAD_BYTES,
+    };
+    use ark_bls12_381::{Fr, G1Affine};
+    use ark_ec::AffineRepr;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_blob_encoding() {
+        let payload: Vec<u8> = (0..40).collect();
+        let elems = blob_field_elements(&payload).unwrap();
+        assert_eq!(elems.len(), FIELD_ELEMENTS_PER_BLOB);
+        // Big-endian, 31 bytes per element
+        assert_eq!(
+            elems[1],
+            (31u64..40).fold(Fr::from(0u64), |acc, byte| acc * Fr::from(256u64)
+                + Fr::from(byte))
+        );
+        assert!(elems[2..].iter().all(|elem| *elem == Fr::from(0u64)));
+
+        assert!(blob_field_elements(&vec![0u8; MAX_BLOB_PAYLOAD_BYTES]).is_some());
+        assert!(blob_field_elements(&vec![0u8; MAX_BLOB_PAYLOAD_BYTES + 1]).is_none());
+    }
+
+    #[test]
+    fn test_blob_versioned_hash() {
+        let mut rng = ark_std::test_rng();
+        let points: Vec<G1Affine> = (0..FIELD_ELEMENTS_PER_BLOB)
+            .map(|_| G1Affine::rand(&mut rng))
+            .collect();
+        let setup = BlobSetup::from_natural_order(points.clone());
+
+        // The empty blob commits to the point at infinity, whose versioned
+        // hash is well known
+        assert_eq!(
+            hex::encode(blob_versioned_hash(&[], &setup).unwrap()),
+            "010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014"
+        );
+        // The first element is evaluated at the first root of unity, stored
+        // first in both orders
+        assert_eq!(setup.0[0], points[0]);
+        assert_eq!(setup.0[1], points[FIELD_ELEMENTS_PER_BLOB / 2]);
+        assert_ne!(
+            blob_versioned_hash(&[1], &setup),
+            blob_versioned_hash(&[2], &setup)
+        );
+
+        // Parse the setup in the format of `c-kzg-4844`
+        let mut txt = format!("{FIELD_ELEMENTS_PER_BLOB}\n65\n");
+        points.iter().for_each(|point| {
+            let mut bytes = Vec::new();
+            point.serialize_compressed(&mut bytes).unwrap();
+            txt.push_str(&hex::encode(bytes));
+            txt.push('\n');
+        });
+        assert_eq!(BlobSetup::from_trusted_setup_txt(&txt).unwrap(), setup);
+        assert!(BlobSetup::from_trusted_setup_txt("4\n65\n").is_err());
+        assert_ne!(
+            setup.hash(),
+            BlobSetup::from_natural_order(vec![G1Affine::generator(); FIELD_ELEMENTS_PER_BLOB])
+                .hash()
+        );
+    }
+}
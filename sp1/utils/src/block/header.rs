@@ -12,9 +12,12 @@ use jf_merkle_tree::{
     MerkleTreeScheme, ToTraversalPath,
 };
 use primitive_types::{H160, H256, U256};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use serde_with::{serde_as, DisplayFromStr};
-use std::io::Read;
+use std::{fmt::Display, io::Read};
 use tagged_base64::tagged;
 
 /// Types for block Merkle tree. This struct is essentially the same as
@@ -34,56 +37,234 @@ pub type FeeMerkleCommitment = <FeeMerkleTree as MerkleTreeScheme>::Commitment;
 #[derive(Clone, Debug, Hash, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct BuilderCommitment(pub [u8; 32]);
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BlockHeader {
-    pub chain_config: ResolvableChainConfig,
-    pub height: u64,
-    pub timestamp: u64,
+pub mod v0_1;
+pub mod v0_3;
+
+/// Version of the header format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl Version {
+    pub const V0_1: Self = Self { major: 0, minor: 1 };
+    pub const V0_2: Self = Self { major: 0, minor: 2 };
+    pub const V0_3: Self = Self { major: 0, minor: 3 };
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}.{}", self.major, self.minor)
+    }
+}
+
+/// A block header of any supported version.
+///
+/// A v0.1 header is serialized as its bare fields, whose first one is the
+/// chain config. Later versions are serialized as
+/// `{"version": {"Version": ...}, "fields": ...}`, with the version taking the
+/// place of the chain config, so that both forms can be told apart by their
+/// first item in JSON as well as in binary encodings.
+#[derive(Debug)]
+pub enum BlockHeader {
+    V1(v0_1::Header),
+    /// v0.2 has the same fields as v0.1, but is versioned
+    V2(v0_1::Header),
+    V3(v0_3::Header),
+}
+
+/// First item of a serialized header.
+#[derive(Serialize, Deserialize)]
+enum EitherOrVersion {
+    Left(ChainConfig),
+    Right(Commitment<ChainConfig>),
+    Version(Version),
+}
 
-    pub l1_head: u64,
+#[derive(Serialize, Deserialize)]
+struct VersionedHeader<Fields> {
+    version: EitherOrVersion,
+    fields: Fields,
+}
 
-    pub l1_finalized: Option<L1BlockInfo>,
+/// Access a field shared by all versions.
+macro_rules! field {
+    ($self:ident.$name:ident) => {
+        match $self {
+            Self::V1(fields) | Self::V2(fields) => &fields.$name,
+            Self::V3(fields) => &fields.$name,
+        }
+    };
+}
 
-    pub payload_commitment: VidCommitment,
-    /// Builder commitment is a Sha256 hash output, 32 bytes.
-    pub builder_commitment: BuilderCommitment,
-    /// A namespace table
-    pub ns_table: NsTable,
-    /// Root Commitment of Block Merkle Tree
-    pub block_merkle_tree_root: BlockMerkleCommitment,
-    /// Serialized root Commitment of `FeeMerkleTree`
-    pub fee_merkle_tree_root: FeeMerkleCommitment,
-    /// Fee information of this block
-    pub fee_info: FeeInfo,
-    // Builder signature is not formally part of the header and not committed.
+macro_rules! field_mut {
+    ($self:ident.$name:ident) => {
+        match $self {
+            Self::V1(fields) | Self::V2(fields) => &mut fields.$name,
+            Self::V3(fields) => &mut fields.$name,
+        }
+    };
+}
+
+impl BlockHeader {
+    pub fn version(&self) -> Version {
+        match self {
+            Self::V1(_) => Version::V0_1,
+            Self::V2(_) => Version::V0_2,
+            Self::V3(_) => Version::V0_3,
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        *field!(self.height)
+    }
+
+    pub fn height_mut(&mut self) -> &mut u64 {
+        field_mut!(self.height)
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        *field!(self.timestamp)
+    }
+
+    pub fn l1_head(&self) -> u64 {
+        *field!(self.l1_head)
+    }
+
+    pub fn payload_commitment(&self) -> &VidCommitment {
+        field!(self.payload_commitment)
+    }
+
+    pub fn payload_commitment_mut(&mut self) -> &mut VidCommitment {
+        field_mut!(self.payload_commitment)
+    }
+
+    pub fn ns_table(&self) -> &NsTable {
+        field!(self.ns_table)
+    }
+
+    pub fn ns_table_mut(&mut self) -> &mut NsTable {
+        field_mut!(self.ns_table)
+    }
+
+    pub fn block_merkle_tree_root(&self) -> &BlockMerkleCommitment {
+        field!(self.block_merkle_tree_root)
+    }
+
+    pub fn block_merkle_tree_root_mut(&mut self) -> &mut BlockMerkleCommitment {
+        field_mut!(self.block_merkle_tree_root)
+    }
+
+    pub fn fee_merkle_tree_root(&self) -> &FeeMerkleCommitment {
+        field!(self.fee_merkle_tree_root)
+    }
+
+    /// Decode the JSON form of a header of any supported version.
+    fn from_json(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        if value.get("version").is_none() {
+            return serde_json::from_value(value).map(Self::V1);
+        }
+        let header: VersionedHeader<serde_json::Value> = serde_json::from_value(value)?;
+        match header.version {
+            EitherOrVersion::Version(Version::V0_2) => {
+                serde_json::from_value(header.fields).map(Self::V2)
+            },
+            EitherOrVersion::Version(Version::V0_3) => {
+                serde_json::from_value(header.fields).map(Self::V3)
+            },
+            EitherOrVersion::Version(version) => Err(de::Error::custom(format!(
+                "unsupported header version {version}"
+            ))),
+            _ => Err(de::Error::custom("expecting a header version")),
+        }
+    }
+}
+
+impl Serialize for BlockHeader {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::V1(fields) => fields.serialize(serializer),
+            Self::V2(fields) => VersionedHeader {
+                version: EitherOrVersion::Version(Version::V0_2),
+                fields,
+            }
+            .serialize(serializer),
+            Self::V3(fields) => VersionedHeader {
+                version: EitherOrVersion::Version(Version::V0_3),
+                fields,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockHeader {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeaderVisitor;
+
+        impl<'de> Visitor<'de> for HeaderVisitor {
+            type Value = BlockHeader;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a block header")
+            }
+
+            // Binary encodings, where the first item is either the chain
+            // config of a v0.1 header or the version of a later one.
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BlockHeader, A::Error> {
+                let first: EitherOrVersion = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let chain_config = match first {
+                    EitherOrVersion::Left(config) => config.into(),
+                    EitherOrVersion::Right(commitment) => commitment.into(),
+                    EitherOrVersion::Version(version) => {
+                        let header = match version {
+                            Version::V0_2 => seq.next_element()?.map(BlockHeader::V2),
+                            Version::V0_3 => seq.next_element()?.map(BlockHeader::V3),
+                            _ => {
+                                return Err(de::Error::custom(format!(
+                                    "unsupported header version {version}"
+                                )))
+                            },
+                        };
+                        return header.ok_or_else(|| de::Error::invalid_length(1, &self));
+                    },
+                };
+                v0_1::Header::deserialize_with_chain_config(chain_config, seq).map(BlockHeader::V1)
+            }
+
+            // Self-describing encodings such as JSON.
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<BlockHeader, A::Error> {
+                let value =
+                    serde_json::Value::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                BlockHeader::from_json(value).map_err(de::Error::custom)
+            }
+        }
+
+        // A struct with as many fields as a v0.1 header, so that binary
+        // encodings can read it all.
+        deserializer.deserialize_struct("BlockHeader", v0_1::FIELDS, HeaderVisitor)
+    }
 }
 
 impl Committable for BlockHeader {
     fn commit(&self) -> Commitment<Self> {
-        let mut bmt_bytes = vec![];
-        self.block_merkle_tree_root
-            .serialize_with_mode(&mut bmt_bytes, ark_serialize::Compress::Yes)
-            .unwrap();
-        let mut fmt_bytes = vec![];
-        self.fee_merkle_tree_root
-            .serialize_with_mode(&mut fmt_bytes, ark_serialize::Compress::Yes)
-            .unwrap();
+        fn versioned<T: Committable>(version: Version, fields: &T) -> Commitment<BlockHeader> {
+            RawCommitmentBuilder::new(&BlockHeader::tag())
+                .u64_field("version_major", version.major as u64)
+                .u64_field("version_minor", version.minor as u64)
+                .field("fields", fields.commit())
+                .finalize()
+        }
 
-        RawCommitmentBuilder::new(&Self::tag())
-            .field("chain_config", self.chain_config.commit())
-            .u64_field("height", self.height)
-            .u64_field("timestamp", self.timestamp)
-            .u64_field("l1_head", self.l1_head)
-            .optional("l1_finalized", &self.l1_finalized)
-            .constant_str("payload_commitment")
-            .fixed_size_bytes(self.payload_commitment.as_ref().as_ref().as_ref())
-            .constant_str("builder_commitment")
-            .fixed_size_bytes(&self.builder_commitment.0)
-            .field("ns_table", self.ns_table.commit())
-            .var_size_field("block_merkle_tree_root", &bmt_bytes)
-            .var_size_field("fee_merkle_tree_root", &fmt_bytes)
-            .field("fee_info", self.fee_info.commit())
-            .finalize()
+        match self {
+            // A v0.1 header is committed without its version
+            Self::V1(fields) => Commitment::from_raw(fields.commit().into()),
+            Self::V2(fields) => versioned(Version::V0_2, fields),
+            Self::V3(fields) => versioned(Version::V0_3, fields),
+        }
     }
 
     fn tag() -> String {
@@ -341,7 +522,118 @@ impl CanonicalDeserialize for FeeAmount {
 
 #[cfg(test)]
 mod tests {
-    use super::BlockHeader;
+    use super::{BlockHeader, Version};
+    use committable::Committable;
+
+    /// Fields of a v0.1 header, tweaked from Espresso's staging testnet.
+    const V1_FIELDS: &str = r#"{
+        "chain_config": {
+            "chain_config": {
+                "Left": {
+                    "chain_id": "888888888",
+                    "max_block_size": "30000000",
+                    "base_fee": "0",
+                    "fee_contract": null,
+                    "fee_recipient": "0x0000000000000000000000000000000000000000"
+                }
+            }
+        },
+        "height": 69781,
+        "timestamp": 1720789795,
+        "l1_head": 5113,
+        "l1_finalized": null,
+        "payload_commitment": "HASH~3XOkaXVZS5e_7xjbbqN22voRnSe_p7Di-U4OPmdCD0JF",
+        "builder_commitment": "BUILDER_COMMITMENT~tEvs0rxqOiMCvfe2R0omNNaphSlUiEDrb2q0IZpRcgA_",
+        "ns_table": {
+            "bytes": "AAAAAA=="
+        },
+        "block_merkle_tree_root": "MERKLE_COMM~02gWBSt2tcz9XfOOO6xEVicluWIIP95BW8I11f2graggAAAAAAAAAJUQAQAAAAAAUQ",
+        "fee_merkle_tree_root": "MERKLE_COMM~yB4_Aqa35_PoskgTpcCR1oVLh6BUdLHIs7erHKWi-usUAAAAAAAAAAEAAAAAAAAAJg",
+        "fee_info": {
+            "account": "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f",
+            "amount": "0"
+        }
+    }"#;
+
+    /// Fields of a v0.3 header.
+    const V3_FIELDS: &str = r#"{
+        "chain_config": {
+            "chain_config": {
+                "Left": {
+                    "chain_id": "888888888",
+                    "max_block_size": "30000000",
+                    "base_fee": "0",
+                    "fee_contract": null,
+                    "fee_recipient": "0x0000000000000000000000000000000000000000",
+                    "bid_recipient": "0x0000000000000000000000000000000000000000"
+                }
+            }
+        },
+        "height": 69781,
+        "timestamp": 1720789795,
+        "l1_head": 5113,
+        "l1_finalized": null,
+        "payload_commitment": "HASH~3XOkaXVZS5e_7xjbbqN22voRnSe_p7Di-U4OPmdCD0JF",
+        "builder_commitment": "BUILDER_COMMITMENT~tEvs0rxqOiMCvfe2R0omNNaphSlUiEDrb2q0IZpRcgA_",
+        "ns_table": {
+            "bytes": "AAAAAA=="
+        },
+        "block_merkle_tree_root": "MERKLE_COMM~02gWBSt2tcz9XfOOO6xEVicluWIIP95BW8I11f2graggAAAAAAAAAJUQAQAAAAAAUQ",
+        "fee_merkle_tree_root": "MERKLE_COMM~yB4_Aqa35_PoskgTpcCR1oVLh6BUdLHIs7erHKWi-usUAAAAAAAAAAEAAAAAAAAAJg",
+        "fee_info": [
+            {
+                "account": "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f",
+                "amount": "0"
+            }
+        ],
+        "auction_results": {
+            "view_number": 42,
+            "winning_bids": [],
+            "reserve_bids": [[1, "https://builder.example.com/"]]
+        }
+    }"#;
+
+    fn versioned(minor: u16, fields: &str) -> String {
+        format!(
+            r#"{{"version": {{"Version": {{"major": 0, "minor": {minor}}}}}, "fields": {fields}}}"#
+        )
+    }
+
+    /// Check that `header` survives JSON and binary encodings, commitment
+    /// included.
+    fn check_round_trip(header: &BlockHeader) {
+        let json: BlockHeader =
+            serde_json::from_str(&serde_json::to_string(header).unwrap()).unwrap();
+        assert_eq!(json.version(), header.version());
+        assert_eq!(json.commit(), header.commit());
+
+        let binary: BlockHeader =
+            bincode::deserialize(&bincode::serialize(header).unwrap()).unwrap();
+        assert_eq!(binary.version(), header.version());
+        assert_eq!(binary.commit(), header.commit());
+    }
+
+    #[test]
+    fn test_versioned_header_serialization() {
+        let v1: BlockHeader = serde_json::from_str(V1_FIELDS).unwrap();
+        let v2: BlockHeader = serde_json::from_str(&versioned(2, V1_FIELDS)).unwrap();
+        let v3: BlockHeader = serde_json::from_str(&versioned(3, V3_FIELDS)).unwrap();
+        assert_eq!(v1.version(), Version::V0_1);
+        assert_eq!(v2.version(), Version::V0_2);
+        assert_eq!(v3.version(), Version::V0_3);
+        for header in [&v1, &v2, &v3] {
+            assert_eq!(header.height(), 69781);
+            check_round_trip(header);
+        }
+
+        // The version is committed, even if the fields are the same
+        assert_ne!(v1.commit(), v2.commit());
+        assert_ne!(v2.commit(), v3.commit());
+
+        // Unknown versions are rejected
+        assert!(serde_json::from_str::<BlockHeader>(&versioned(4, V3_FIELDS)).is_err());
+        assert!(serde_json::from_str::<BlockHeader>(&versioned(3, V1_FIELDS)).is_err());
+    }
 
     #[test]
     fn test_header_serialization() {
@@ -0,0 +1,118 @@
+//! Header fields of Espresso v0.1, also used unchanged by v0.2.
+
+use super::{
+    BlockMerkleCommitment, BuilderCommitment, FeeInfo, FeeMerkleCommitment, L1BlockInfo,
+    ResolvableChainConfig,
+};
+use crate::{block::payload::VidCommitment, ns_table::NsTable};
+use ark_serialize::CanonicalSerialize;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{
+    de::{self, SeqAccess},
+    Deserialize, Serialize,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub chain_config: ResolvableChainConfig,
+    pub height: u64,
+    pub timestamp: u64,
+
+    pub l1_head: u64,
+
+    pub l1_finalized: Option<L1BlockInfo>,
+
+    pub payload_commitment: VidCommitment,
+    /// Builder commitment is a Sha256 hash output, 32 bytes.
+    pub builder_commitment: BuilderCommitment,
+    /// A namespace table
+    pub ns_table: NsTable,
+    /// Root Commitment of Block Merkle Tree
+    pub block_merkle_tree_root: BlockMerkleCommitment,
+    /// Serialized root Commitment of `FeeMerkleTree`
+    pub fee_merkle_tree_root: FeeMerkleCommitment,
+    /// Fee information of this block
+    pub fee_info: FeeInfo,
+    // Builder signature is not formally part of the header and not committed.
+}
+
+/// Names of the fields of a v0.1 header, in serialization order.
+pub(super) const FIELDS: &[&str] = &[
+    "chain_config",
+    "height",
+    "timestamp",
+    "l1_head",
+    "l1_finalized",
+    "payload_commitment",
+    "builder_commitment",
+    "ns_table",
+    "block_merkle_tree_root",
+    "fee_merkle_tree_root",
+    "fee_info",
+];
+
+impl Header {
+    /// Deserialize the fields following `chain_config` from a binary
+    /// sequence, whose first element has been consumed to detect the version.
+    pub(super) fn deserialize_with_chain_config<'de, A: SeqAccess<'de>>(
+        chain_config: ResolvableChainConfig,
+        mut seq: A,
+    ) -> Result<Self, A::Error> {
+        fn next<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(
+            seq: &mut A,
+            idx: usize,
+        ) -> Result<T, A::Error> {
+            seq.next_element()?
+                .ok_or_else(|| de::Error::invalid_length(idx, &"a v0.1 block header"))
+        }
+
+        Ok(Self {
+            chain_config,
+            height: next(&mut seq, 1)?,
+            timestamp: next(&mut seq, 2)?,
+            l1_head: next(&mut seq, 3)?,
+            l1_finalized: next(&mut seq, 4)?,
+            payload_commitment: next(&mut seq, 5)?,
+            builder_commitment: next(&mut seq, 6)?,
+            ns_table: next(&mut seq, 7)?,
+            block_merkle_tree_root: next(&mut seq, 8)?,
+            fee_merkle_tree_root: next(&mut seq, 9)?,
+            fee_info: next(&mut seq, 10)?,
+        })
+    }
+}
+
+impl Committable for Header {
+    fn commit(&self) -> Commitment<Self> {
+        let mut bmt_bytes = vec![];
+        self.block_merkle_tree_root
+            .serialize_with_mode(&mut bmt_bytes, ark_serialize::Compress::Yes)
+            .unwrap();
+        let mut fmt_bytes = vec![];
+        self.fee_merkle_tree_root
+            .serialize_with_mode(&mut fmt_bytes, ark_serialize::Compress::Yes)
+            .unwrap();
+
+        RawCommitmentBuilder::new(&Self::tag())
+            .field("chain_config", self.chain_config.commit())
+            .u64_field("height", self.height)
+            .u64_field("timestamp", self.timestamp)
+            .u64_field("l1_head", self.l1_head)
+            .optional("l1_finalized", &self.l1_finalized)
+            .constant_str("payload_commitment")
+            .fixed_size_bytes(self.payload_commitment.as_bytes())
+            .constant_str("builder_commitment")
+            .fixed_size_bytes(&self.builder_commitment.0)
+            .field("ns_table", self.ns_table.commit())
+            .var_size_field("block_merkle_tree_root", &bmt_bytes)
+            .var_size_field("fee_merkle_tree_root", &fmt_bytes)
+            .field("fee_info", self.fee_info.commit())
+            .finalize()
+    }
+
+    fn tag() -> String {
+        // We use the tag "BLOCK" since blocks are identified by the hash of their
+        // header. This will thus be more intuitive to users than "HEADER".
+        "BLOCK".into()
+    }
+}
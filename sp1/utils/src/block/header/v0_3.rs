@@ -0,0 +1,309 @@
+//! Header fields of Espresso v0.3, which adds a fee for each builder of the
+//! block, the bid recipient to the chain config, and the results of the
+//! solver auction.
+
+use super::{
+    BlockMerkleCommitment, BuilderCommitment, FeeAccount, FeeAmount, FeeInfo, FeeMerkleCommitment,
+    L1BlockInfo,
+};
+use crate::{block::payload::VidCommitment, ns_table::NsTable};
+use ark_serialize::CanonicalSerialize;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use either::Either;
+use primitive_types::{H160, U256};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub chain_config: ResolvableChainConfig,
+    pub height: u64,
+    pub timestamp: u64,
+
+    pub l1_head: u64,
+
+    pub l1_finalized: Option<L1BlockInfo>,
+
+    pub payload_commitment: VidCommitment,
+    /// Builder commitment is a Sha256 hash output, 32 bytes.
+    pub builder_commitment: BuilderCommitment,
+    /// A namespace table
+    pub ns_table: NsTable,
+    /// Root Commitment of Block Merkle Tree
+    pub block_merkle_tree_root: BlockMerkleCommitment,
+    /// Serialized root Commitment of `FeeMerkleTree`
+    pub fee_merkle_tree_root: FeeMerkleCommitment,
+    /// Fee information of each builder contributing to this block
+    pub fee_info: Vec<FeeInfo>,
+    /// Results of the solver auction for this block's view, if any
+    pub auction_results: Option<SolverAuctionResults>,
+    // Builder signatures are not formally part of the header and not committed.
+}
+
+impl Committable for Header {
+    fn commit(&self) -> Commitment<Self> {
+        let mut bmt_bytes = vec![];
+        self.block_merkle_tree_root
+            .serialize_with_mode(&mut bmt_bytes, ark_serialize::Compress::Yes)
+            .unwrap();
+        let mut fmt_bytes = vec![];
+        self.fee_merkle_tree_root
+            .serialize_with_mode(&mut fmt_bytes, ark_serialize::Compress::Yes)
+            .unwrap();
+
+        RawCommitmentBuilder::new(&Self::tag())
+            .field("chain_config", self.chain_config.commit())
+            .u64_field("height", self.height)
+            .u64_field("timestamp", self.timestamp)
+            .u64_field("l1_head", self.l1_head)
+            .optional("l1_finalized", &self.l1_finalized)
+            .constant_str("payload_commitment")
+            .fixed_size_bytes(self.payload_commitment.as_bytes())
+            .constant_str("builder_commitment")
+            .fixed_size_bytes(&self.builder_commitment.0)
+            .field("ns_table", self.ns_table.commit())
+            .var_size_field("block_merkle_tree_root", &bmt_bytes)
+            .var_size_field("fee_merkle_tree_root", &fmt_bytes)
+            .array_field(
+                "fee_info",
+                &self
+                    .fee_info
+                    .iter()
+                    .map(Committable::commit)
+                    .collect::<Vec<_>>(),
+            )
+            .optional("auction_results", &self.auction_results)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "BLOCK".into()
+    }
+}
+
+/// Global variables for an Espresso blockchain, since v0.3.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub struct ChainConfig {
+    /// Espresso chain ID
+    pub chain_id: U256,
+
+    /// Maximum size in bytes of a block
+    #[serde_as(as = "DisplayFromStr")]
+    pub max_block_size: u64,
+
+    /// Minimum fee in WEI per byte of payload
+    pub base_fee: U256,
+
+    /// Fee contract H160 on L1.
+    pub fee_contract: Option<H160>,
+
+    /// Account that receives sequencing fees.
+    pub fee_recipient: H160,
+
+    /// Account that receives sequencing bids.
+    pub bid_recipient: Option<FeeAccount>,
+}
+
+impl Committable for ChainConfig {
+    fn tag() -> String {
+        "CHAIN_CONFIG".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        let mut chain_id_bytes = [0u8; 32];
+        self.chain_id.to_little_endian(&mut chain_id_bytes);
+
+        let mut base_fee_bytes = [0u8; 32];
+        self.base_fee.to_little_endian(&mut base_fee_bytes);
+
+        let comm = committable::RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("chain_id", &chain_id_bytes)
+            .u64_field("max_block_size", self.max_block_size)
+            .fixed_size_field("base_fee", &base_fee_bytes)
+            .fixed_size_field("fee_recipient", &self.fee_recipient.to_fixed_bytes());
+        let comm = if let Some(addr) = self.fee_contract {
+            comm.u64_field("fee_contract", 1).fixed_size_bytes(&addr.0)
+        } else {
+            comm.u64_field("fee_contract", 0)
+        };
+        // An absent bid recipient isn't committed, so that a config without it
+        // keeps the same commitment as before v0.3. Unlike `fee_contract`, a
+        // present one isn't flagged either, as in espresso-sequencer.
+        let comm = if let Some(bid_recipient) = self.bid_recipient {
+            comm.fixed_size_field("bid_recipient", &bid_recipient.0.to_fixed_bytes())
+        } else {
+            comm
+        };
+        comm.finalize()
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Deserialize, Serialize, Eq, Hash)]
+pub struct ResolvableChainConfig {
+    chain_config: Either<ChainConfig, Commitment<ChainConfig>>,
+}
+
+impl ResolvableChainConfig {
+    pub fn commit(&self) -> Commitment<ChainConfig> {
+        match self.chain_config {
+            Either::Left(config) => config.commit(),
+            Either::Right(commitment) => commitment,
+        }
+    }
+    pub fn resolve(self) -> Option<ChainConfig> {
+        match self.chain_config {
+            Either::Left(config) => Some(config),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl From<Commitment<ChainConfig>> for ResolvableChainConfig {
+    fn from(value: Commitment<ChainConfig>) -> Self {
+        Self {
+            chain_config: Either::Right(value),
+        }
+    }
+}
+
+impl From<ChainConfig> for ResolvableChainConfig {
+    fn from(value: ChainConfig) -> Self {
+        Self {
+            chain_config: Either::Left(value),
+        }
+    }
+}
+
+/// Namespace ID as used by the solver auction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NamespaceId(pub u64);
+
+/// An ECDSA signature by a fee account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FeeAccountSignature {
+    pub r: U256,
+    pub s: U256,
+    pub v: u64,
+}
+
+impl FeeAccountSignature {
+    /// The 65-byte `r || s || v` encoding.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        self.r.to_big_endian(&mut bytes[..32]);
+        self.s.to_big_endian(&mut bytes[32..64]);
+        bytes[64] = self.v as u8;
+        bytes
+    }
+}
+
+/// Results of the solver auction for a view.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverAuctionResults {
+    pub view_number: u64,
+    pub winning_bids: Vec<BidTx>,
+    /// URLs of reserve builders for each namespace
+    pub reserve_bids: Vec<(NamespaceId, String)>,
+}
+
+impl Committable for SolverAuctionResults {
+    fn tag() -> String {
+        "SOLVER_AUCTION_RESULTS".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .u64_field("view_number", self.view_number)
+            .array_field(
+                "winning_bids",
+                &self
+                    .winning_bids
+                    .iter()
+                    .map(Committable::commit)
+                    .collect::<Vec<_>>(),
+            )
+            .array_field(
+                "reserve_bids",
+                &self
+                    .reserve_bids
+                    .iter()
+                    .map(|(ns_id, url)| {
+                        RawCommitmentBuilder::<Self>::new("RESERVE_BID")
+                            .u64_field("namespace", ns_id.0)
+                            .var_size_field("url", url.as_bytes())
+                            .finalize()
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .finalize()
+    }
+}
+
+/// A signed bid of a builder in the solver auction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BidTx {
+    pub body: BidTxBody,
+    pub signature: FeeAccountSignature,
+}
+
+impl Committable for BidTx {
+    fn tag() -> String {
+        "BID_TX".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new(&Self::tag())
+            .field("body", self.body.commit())
+            .fixed_size_field("signature", &self.signature.to_bytes())
+            .finalize()
+    }
+}
+
+/// Content of a bid in the solver auction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BidTxBody {
+    /// Account paying the bid
+    pub account: FeeAccount,
+    pub gas_price: FeeAmount,
+    pub bid_amount: FeeAmount,
+    /// URL of the builder
+    pub url: String,
+    /// View number the bid is for
+    pub view: u64,
+    /// Namespaces the bid is for
+    pub namespaces: Vec<NamespaceId>,
+}
+
+impl Committable for BidTxBody {
+    fn tag() -> String {
+        "BID_TX_BODY".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        let mut gas_price_bytes = [0u8; 32];
+        self.gas_price.0.to_little_endian(&mut gas_price_bytes);
+        let mut bid_amount_bytes = [0u8; 32];
+        self.bid_amount.0.to_little_endian(&mut bid_amount_bytes);
+
+        RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("account", &self.account.0.to_fixed_bytes())
+            .fixed_size_field("gas_price", &gas_price_bytes)
+            .fixed_size_field("bid_amount", &bid_amount_bytes)
+            .var_size_field("url", self.url.as_bytes())
+            .u64_field("view", self.view)
+            .array_field(
+                "namespaces",
+                &self
+                    .namespaces
+                    .iter()
+                    .map(|ns_id| {
+                        RawCommitmentBuilder::<Self>::new("namespace")
+                            .u64(ns_id.0)
+                            .finalize()
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .finalize()
+    }
+}
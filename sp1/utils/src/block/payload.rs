@@ -1,5 +1,6 @@
 //! Define the payload for an espresso block.
 use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use jf_pcs::{
     prelude::UnivariateUniversalParams, univariate_kzg::UnivariateKzgPCS,
@@ -12,9 +13,13 @@ use jf_vid::{
 use primitive_types::H256;
 use serde::{de::Error as _, ser::Error as _, Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use tagged_base64::tagged;
+use tagged_base64::{tagged, Tagged, TaggedBase64};
 
 use super::RollupCommitment;
+use crate::{
+    commitment::{RollupCommitmentScheme, SelectedScheme},
+    vid::{AvidMCommitment, AvidMCommon, AvidMNsProof},
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Payload(pub Vec<u8>);
@@ -24,31 +29,109 @@ type E = Bn254;
 /// Private type alias for the hash type parameter for [`Advz`].
 type H = Sha256;
 
-/// VidScheme
+/// ADVZ, the VID scheme of blocks before the AvidM upgrade, see [`crate::vid`]
 pub type Vid = Advz<E, H>;
 
-/// VID commitment type
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+/// ADVZ commitment, tagged "HASH" as in headers before the AvidM upgrade
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 #[tagged("HASH")]
-pub struct VidCommitment(pub <Vid as VidScheme>::Commit);
+pub struct AdvzCommitment(pub <Vid as VidScheme>::Commit);
 
-impl AsRef<<Vid as VidScheme>::Commit> for VidCommitment {
+impl AsRef<<Vid as VidScheme>::Commit> for AdvzCommitment {
     fn as_ref(&self) -> &<Vid as VidScheme>::Commit {
         &self.0
     }
 }
 
-/// Type of common data for VID scheme
+/// Payload commitment of a block, tagged with the VID scheme dispersing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VidCommitment {
+    V0(AdvzCommitment),
+    V1(AvidMCommitment),
+}
+
+/// Binary form of [`VidCommitment`], the human-readable one being the tagged
+/// base64 of either version, told apart by its tag.
+#[derive(Serialize, Deserialize)]
+enum VersionedVidCommitment {
+    V0(AdvzCommitment),
+    V1(AvidMCommitment),
+}
+
+impl VidCommitment {
+    /// Bytes of the commitment, as committed in the block header.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        match self {
+            Self::V0(commit) => commit.as_ref().as_ref().as_ref(),
+            Self::V1(commit) => commit.as_ref().as_ref().as_ref(),
+        }
+    }
+}
+
+impl From<AdvzCommitment> for VidCommitment {
+    fn from(commit: AdvzCommitment) -> Self {
+        Self::V0(commit)
+    }
+}
+
+impl From<AvidMCommitment> for VidCommitment {
+    fn from(commit: AvidMCommitment) -> Self {
+        Self::V1(commit)
+    }
+}
+
+impl Serialize for VidCommitment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match (self, serializer.is_human_readable()) {
+            (Self::V0(commit), true) => commit.serialize(serializer),
+            (Self::V1(commit), true) => commit.serialize(serializer),
+            (Self::V0(commit), false) => {
+                VersionedVidCommitment::V0(commit.clone()).serialize(serializer)
+            },
+            (Self::V1(commit), false) => VersionedVidCommitment::V1(*commit).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VidCommitment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let tb64 = TaggedBase64::deserialize(deserializer)?;
+            if tb64.tag() == AvidMCommitment::tag() {
+                AvidMCommitment::try_from(tb64)
+                    .map(Self::V1)
+                    .map_err(D::Error::custom)
+            } else {
+                AdvzCommitment::try_from(tb64)
+                    .map(Self::V0)
+                    .map_err(D::Error::custom)
+            }
+        } else {
+            Ok(match VersionedVidCommitment::deserialize(deserializer)? {
+                VersionedVidCommitment::V0(commit) => Self::V0(commit),
+                VersionedVidCommitment::V1(commit) => Self::V1(commit),
+            })
+        }
+    }
+}
+
+/// Common data of ADVZ
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct VidCommon(pub <Vid as VidScheme>::Common);
+pub struct AdvzCommon(pub <Vid as VidScheme>::Common);
 
-impl AsRef<<Vid as VidScheme>::Common> for VidCommon {
+impl AsRef<<Vid as VidScheme>::Common> for AdvzCommon {
     fn as_ref(&self) -> &<Vid as VidScheme>::Common {
         &self.0
     }
 }
 
-impl Serialize for VidCommon {
+impl Serialize for AdvzCommon {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -61,7 +144,7 @@ impl Serialize for VidCommon {
     }
 }
 
-impl<'de> Deserialize<'de> for VidCommon {
+impl<'de> Deserialize<'de> for AdvzCommon {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
@@ -71,14 +154,94 @@ impl<'de> Deserialize<'de> for VidCommon {
             &*bytes,
         )
         .map_err(|e| D::Error::custom(format!("{e:?}")))
-        .map(VidCommon)
+        .map(AdvzCommon)
+    }
+}
+
+/// Type of common data for VID scheme, tagged with the scheme.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum VidCommon {
+    V0(AdvzCommon),
+    V1(AvidMCommon),
+}
+
+impl From<AdvzCommon> for VidCommon {
+    fn from(common: AdvzCommon) -> Self {
+        Self::V0(common)
+    }
+}
+
+impl From<AvidMCommon> for VidCommon {
+    fn from(common: AvidMCommon) -> Self {
+        Self::V1(common)
     }
 }
 
-/// Public parameters to setup the VID scheme
+/// Public parameters to setup the VID scheme, i.e. the KZG SRS.
+///
+/// Since the zkVM can't take the SRS of an Espresso network as input, `srs`
+/// may be truncated to its first chunks of [`SRS_CHUNK_SIZE`] powers of G1,
+/// with the digests of the omitted chunks, so that [`compute_vid_param_hash`]
+/// is the same as for the full SRS.
 /// Manual (de)serialization to avoid the expensive validity check.
-#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct VidParam(pub UnivariateUniversalParams<E>);
+#[derive(Debug, Clone)]
+pub struct VidParam {
+    pub srs: UnivariateUniversalParams<E>,
+    /// Digests of the chunks of powers of G1 omitted from `srs`, in order
+    pub omitted_chunks: Vec<H256>,
+}
+
+impl VidParam {
+    /// The full SRS `srs`.
+    pub fn new(srs: UnivariateUniversalParams<E>) -> Self {
+        Self {
+            srs,
+            omitted_chunks: vec![],
+        }
+    }
+
+    /// Truncate the SRS to its first `num_powers` powers of G1, rounded up to
+    /// whole chunks, keeping the same [`compute_vid_param_hash`].
+    pub fn truncate(&self, num_powers: usize) -> Self {
+        let num_powers = num_powers.div_ceil(SRS_CHUNK_SIZE) * SRS_CHUNK_SIZE;
+        if num_powers >= self.srs.powers_of_g.len() {
+            return self.clone();
+        }
+        let omitted_chunks = self.srs.powers_of_g[num_powers..]
+            .chunks(SRS_CHUNK_SIZE)
+            .map(srs_chunk_digest)
+            .chain(self.omitted_chunks.iter().copied())
+            .collect();
+        Self {
+            srs: UnivariateUniversalParams {
+                powers_of_g: self.srs.powers_of_g[..num_powers].to_vec(),
+                h: self.srs.h,
+                beta_h: self.srs.beta_h,
+                powers_of_h: self.srs.powers_of_h.clone(),
+            },
+            omitted_chunks,
+        }
+    }
+
+    /// Truncate the SRS to the powers needed to verify the namespace proofs of
+    /// blocks with these VID common data, see [`vid_scheme`].
+    pub fn truncate_for<'a>(&self, commons: impl IntoIterator<Item = &'a VidCommon>) -> Self {
+        let num_powers = commons
+            .into_iter()
+            .filter_map(|common| match common {
+                VidCommon::V0(common) => {
+                    let num_storage_nodes =
+                        <Vid as VidScheme>::get_num_storage_nodes(common.as_ref());
+                    Some((1 << num_storage_nodes.ilog2()) + 1)
+                },
+                // AvidM doesn't use the SRS
+                VidCommon::V1(_) => None,
+            })
+            .max()
+            .unwrap_or(1);
+        self.truncate(num_powers)
+    }
+}
 
 impl Serialize for VidParam {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -86,45 +249,65 @@ impl Serialize for VidParam {
         S: serde::Serializer,
     {
         let mut bytes = Vec::new();
-        self.0
+        self.srs
             .serialize_uncompressed(&mut bytes)
             .map_err(|e| S::Error::custom(format!("{e:?}")))?;
-        Serialize::serialize(&bytes, serializer)
+        Serialize::serialize(&(bytes, &self.omitted_chunks), serializer)
     }
 }
 
+/// Serialized forms of [`VidParam`] in self-describing formats such as JSON:
+/// the current one, and the full SRS alone, as saved before it could be
+/// truncated.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VidParamRepr {
+    Truncatable(Vec<u8>, Vec<H256>),
+    Legacy(Vec<u8>),
+}
+
 impl<'de> Deserialize<'de> for VidParam {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let bytes = <Vec<u8> as Deserialize>::deserialize(deserializer)?;
-        <UnivariateUniversalParams<E> as CanonicalDeserialize>::deserialize_uncompressed_unchecked(
-            &*bytes,
-        )
-        .map_err(|e| D::Error::custom(format!("{e:?}")))
-        .map(VidParam)
+        // Non self-describing formats can't fall back to the legacy form,
+        // see `inputs` in the script crate for bincode
+        let (bytes, omitted_chunks) = if deserializer.is_human_readable() {
+            match VidParamRepr::deserialize(deserializer)? {
+                VidParamRepr::Truncatable(bytes, omitted_chunks) => (bytes, omitted_chunks),
+                VidParamRepr::Legacy(bytes) => (bytes, vec![]),
+            }
+        } else {
+            <(Vec<u8>, Vec<H256>) as Deserialize>::deserialize(deserializer)?
+        };
+        let srs = UnivariateUniversalParams::<E>::deserialize_uncompressed_unchecked(&*bytes)
+            .map_err(|e| D::Error::custom(format!("{e:?}")))?;
+        Ok(VidParam {
+            srs,
+            omitted_chunks,
+        })
     }
 }
 
 type F = <UnivariateKzgPCS<E> as PolynomialCommitmentScheme>::Evaluation;
-/// Namespace Proof type
+/// Namespace proof of ADVZ
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct NsProof(pub LargeRangeProof<F>);
+pub struct AdvzNsProof(pub LargeRangeProof<F>);
 
-impl From<LargeRangeProof<F>> for NsProof {
+impl From<LargeRangeProof<F>> for AdvzNsProof {
     fn from(proof: LargeRangeProof<F>) -> Self {
         Self(proof)
     }
 }
 
-impl AsRef<LargeRangeProof<F>> for NsProof {
+impl AsRef<LargeRangeProof<F>> for AdvzNsProof {
     fn as_ref(&self) -> &LargeRangeProof<F> {
         &self.0
     }
 }
 
-impl Serialize for NsProof {
+impl Serialize for AdvzNsProof {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -137,7 +320,7 @@ impl Serialize for NsProof {
     }
 }
 
-impl<'de> Deserialize<'de> for NsProof {
+impl<'de> Deserialize<'de> for AdvzNsProof {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
@@ -145,21 +328,78 @@ impl<'de> Deserialize<'de> for NsProof {
         let bytes = <Vec<u8> as Deserialize>::deserialize(deserializer)?;
         <LargeRangeProof<F> as CanonicalDeserialize>::deserialize_uncompressed_unchecked(&*bytes)
             .map_err(|e| D::Error::custom(format!("{e:?}")))
-            .map(NsProof)
+            .map(AdvzNsProof)
     }
 }
 
-/// Dummy rollup payload commit
+/// Namespace Proof type, tagged with the VID scheme.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum NsProof {
+    V0(AdvzNsProof),
+    V1(AvidMNsProof),
+}
+
+impl From<AdvzNsProof> for NsProof {
+    fn from(proof: AdvzNsProof) -> Self {
+        Self::V0(proof)
+    }
+}
+
+impl From<AvidMNsProof> for NsProof {
+    fn from(proof: AvidMNsProof) -> Self {
+        Self::V1(proof)
+    }
+}
+
+/// Commit to the rollup payload with the scheme selected by cargo features,
+/// see [`crate::commitment`].
 pub fn rollup_commit(payload: &Payload) -> RollupCommitment {
-    let bytes: [u8; 32] = Sha256::digest(&payload.0).into();
+    SelectedScheme::commit(&payload.0)
+}
+
+/// Number of powers of G1 per chunk of the SRS in [`compute_vid_param_hash`].
+pub const SRS_CHUNK_SIZE: usize = 1024;
+
+/// Sha256 of a chunk of powers of G1, in uncompressed form.
+fn srs_chunk_digest(chunk: &[<E as Pairing>::G1Affine]) -> H256 {
+    let mut hasher = Sha256::new();
+    chunk.iter().for_each(|power| {
+        let mut bytes = Vec::new();
+        power.serialize_uncompressed(&mut bytes).unwrap();
+        hasher.update(bytes);
+    });
+    let bytes: [u8; 32] = hasher.finalize().into();
     bytes.into()
 }
 
+/// Hash identifying the full SRS, even when given truncated:
+/// `Sha256(len(powers_of_h) || h || beta_h || powers_of_h || chunk digests)`,
+/// where the length is a little-endian `u64`, points are uncompressed, and the
+/// powers of G1 are split into chunks of [`SRS_CHUNK_SIZE`] hashed with
+/// Sha256, followed by the digests of the omitted ones.
 pub fn compute_vid_param_hash(param: &VidParam) -> H256 {
-    let bytes: [u8; 32] = Sha256::digest(bincode::serialize(param).unwrap()).into();
+    let srs = &param.srs;
+    let mut hasher = Sha256::new();
+    hasher.update((srs.powers_of_h.len() as u64).to_le_bytes());
+    [srs.h, srs.beta_h]
+        .iter()
+        .chain(&srs.powers_of_h)
+        .for_each(|power| {
+            let mut bytes = Vec::new();
+            power.serialize_uncompressed(&mut bytes).unwrap();
+            hasher.update(bytes);
+        });
+    srs.powers_of_g
+        .chunks(SRS_CHUNK_SIZE)
+        .map(srs_chunk_digest)
+        .chain(param.omitted_chunks.iter().copied())
+        .for_each(|digest| hasher.update(digest));
+    let bytes: [u8; 32] = hasher.finalize().into();
     bytes.into()
 }
 
+/// Degree of the SRS of Espresso networks, as opposed to the low degree one
+/// of the mock inputs.
 pub const SRS_DEGREE: usize = 2u64.pow(20) as usize + 2;
 
 /// Construct a VID scheme given the number of storage nodes.
@@ -167,8 +407,67 @@ pub const SRS_DEGREE: usize = 2u64.pow(20) as usize + 2;
 pub fn vid_scheme(num_storage_nodes: u32, param: &VidParam) -> Vid {
     let recovery_threshold = 1 << num_storage_nodes.ilog2();
 
-    Advz::new(num_storage_nodes, recovery_threshold, &param.0).unwrap_or_else(|err| {
+    Advz::new(num_storage_nodes, recovery_threshold, &param.srs).unwrap_or_else(|err| {
         panic!("advz construction failure: (num_storage nodes,recovery_threshold)=({num_storage_nodes},{recovery_threshold}); \
                 error: {err}")
   })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_vid_param_hash, vid_scheme, VidParam, SRS_CHUNK_SIZE};
+    use ark_bn254::Bn254;
+    use ark_serialize::CanonicalSerialize;
+    use jf_pcs::{prelude::UnivariateKzgPCS, PolynomialCommitmentScheme};
+    use jf_vid::VidScheme;
+
+    #[test]
+    fn test_truncated_vid_param_hash() {
+        let mut rng = ark_std::test_rng();
+        let param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut rng, 2 * SRS_CHUNK_SIZE + 10)
+                .unwrap(),
+        );
+        let vid_param_hash = compute_vid_param_hash(&param);
+
+        // Rounded up to a whole chunk, with the digests of the last two
+        let truncated = param.truncate(8);
+        assert_eq!(truncated.srs.powers_of_g.len(), SRS_CHUNK_SIZE);
+        assert_eq!(truncated.omitted_chunks.len(), 2);
+        assert_eq!(compute_vid_param_hash(&truncated), vid_param_hash);
+        assert_eq!(
+            compute_vid_param_hash(&truncated.truncate(1)),
+            vid_param_hash
+        );
+        let truncated: VidParam =
+            bincode::deserialize(&bincode::serialize(&truncated).unwrap()).unwrap();
+        assert_eq!(compute_vid_param_hash(&truncated), vid_param_hash);
+        let truncated: VidParam =
+            serde_json::from_slice(&serde_json::to_vec(&truncated).unwrap()).unwrap();
+        assert_eq!(compute_vid_param_hash(&truncated), vid_param_hash);
+
+        // JSON saved before truncation holds the full SRS alone
+        let mut bytes = Vec::new();
+        param.srs.serialize_uncompressed(&mut bytes).unwrap();
+        let legacy: VidParam =
+            serde_json::from_slice(&serde_json::to_vec(&bytes).unwrap()).unwrap();
+        assert!(legacy.omitted_chunks.is_empty());
+        assert_eq!(compute_vid_param_hash(&legacy), vid_param_hash);
+
+        // The truncated SRS is enough for a few storage nodes
+        let payload = vec![1u8; 100];
+        let commit = vid_scheme(10, &param).commit_only(&payload).unwrap();
+        assert_eq!(
+            vid_scheme(10, &truncated).commit_only(&payload).unwrap(),
+            commit
+        );
+
+        // Omitted chunks are bound by the hash
+        let mut other = param.truncate(8);
+        other.omitted_chunks[1].0[0] ^= 1;
+        assert_ne!(compute_vid_param_hash(&other), vid_param_hash);
+        let mut other = param.clone();
+        other.srs.powers_of_g.pop();
+        assert_ne!(compute_vid_param_hash(&other), vid_param_hash);
+    }
+}
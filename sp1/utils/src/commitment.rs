@@ -0,0 +1,186 @@
+//! Commitment schemes for the rollup transactions, matching the way a VM
+//! prover commits to its batch.
+//!
+//! The scheme used by the programs is selected at compile time: SHA-256 by
+//! default, Keccak-256 with the `commit-keccak` feature, or Poseidon over
+//! BN254 with the `commit-poseidon` feature. As cargo unifies the features of
+//! a workspace, both may end up enabled, in which case Poseidon takes
+//! precedence.
+
+use crate::block::RollupCommitment;
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use light_poseidon::{Poseidon, PoseidonHasher};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// A way to commit to the rollup transactions in bytes form.
+pub trait RollupCommitmentScheme {
+    /// Identifier recorded in the public inputs.
+    const ID: CommitmentSchemeId;
+
+    /// Commit to `payload`.
+    fn commit(payload: &[u8]) -> RollupCommitment;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Identifier of a [`RollupCommitmentScheme`].
+pub enum CommitmentSchemeId {
+    Sha256,
+    Keccak256,
+    PoseidonBn254,
+    /// Versioned hash of the EIP-4844 blob holding the payload, see
+    /// [`crate::blob`]
+    Eip4844Blob,
+}
+
+impl CommitmentSchemeId {
+    /// Commit to `payload` with the identified scheme, or `None` for
+    /// [`CommitmentSchemeId::Eip4844Blob`] which also needs the trusted setup.
+    pub fn commit(&self, payload: &[u8]) -> Option<RollupCommitment> {
+        match self {
+            Self::Sha256 => Some(Sha256Commitment::commit(payload)),
+            Self::Keccak256 => Some(Keccak256Commitment::commit(payload)),
+            Self::PoseidonBn254 => Some(PoseidonBn254Commitment::commit(payload)),
+            Self::Eip4844Blob => None,
+        }
+    }
+}
+
+/// `Sha256(payload)`
+pub struct Sha256Commitment;
+
+impl RollupCommitmentScheme for Sha256Commitment {
+    const ID: CommitmentSchemeId = CommitmentSchemeId::Sha256;
+
+    fn commit(payload: &[u8]) -> RollupCommitment {
+        let bytes: [u8; 32] = Sha256::digest(payload).into();
+        bytes.into()
+    }
+}
+
+/// `Keccak256(payload)`
+pub struct Keccak256Commitment;
+
+impl RollupCommitmentScheme for Keccak256Commitment {
+    const ID: CommitmentSchemeId = CommitmentSchemeId::Keccak256;
+
+    fn commit(payload: &[u8]) -> RollupCommitment {
+        let bytes: [u8; 32] = Keccak256::digest(payload).into();
+        bytes.into()
+    }
+}
+
+/// Poseidon over the BN254 scalar field, with the circom parameters.
+///
+/// The payload is split into 31-byte big-endian chunks, each fitting in a
+/// field element, which are absorbed one by one into a chain starting from
+/// the payload byte length: `h_0 = len`, `h_{i+1} = Poseidon(h_i, chunk_i)`.
+/// The final element is output in 32 big-endian bytes.
+pub struct PoseidonBn254Commitment;
+
+/// Number of payload bytes packed into a field element.
+const POSEIDON_CHUNK_BYTE_LEN: usize = 31;
+
+impl RollupCommitmentScheme for PoseidonBn254Commitment {
+    const ID: CommitmentSchemeId = CommitmentSchemeId::PoseidonBn254;
+
+    fn commit(payload: &[u8]) -> RollupCommitment {
+        let mut poseidon = Poseidon::<Fr>::new_circom(2).unwrap();
+        let digest = payload.chunks(POSEIDON_CHUNK_BYTE_LEN).fold(
+            Fr::from(payload.len() as u64),
+            |acc, chunk| {
+                poseidon
+                    .hash(&[acc, Fr::from_be_bytes_mod_order(chunk)])
+                    .unwrap()
+            },
+        );
+        let bytes: [u8; 32] = digest.into_bigint().to_bytes_be().try_into().unwrap();
+        bytes.into()
+    }
+}
+
+/// The scheme selected by cargo features.
+#[cfg(not(any(feature = "commit-keccak", feature = "commit-poseidon")))]
+pub type SelectedScheme = Sha256Commitment;
+/// The scheme selected by cargo features.
+#[cfg(all(feature = "commit-keccak", not(feature = "commit-poseidon")))]
+pub type SelectedScheme = Keccak256Commitment;
+/// The scheme selected by cargo features.
+#[cfg(feature = "commit-poseidon")]
+pub type SelectedScheme = PoseidonBn254Commitment;
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CommitmentSchemeId, Keccak256Commitment, PoseidonBn254Commitment, RollupCommitmentScheme,
+        Sha256Commitment,
+    };
+    use crate::block::RollupCommitment;
+
+    #[test]
+    fn test_rollup_commitment_vectors() {
+        // FIPS 180-2 and Keccak team vectors
+        for (payload, sha256, keccak256) in [
+            (
+                &b""[..],
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            ),
+            (
+                &b"abc"[..],
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+            ),
+        ] {
+            assert_eq!(hex::encode(Sha256Commitment::commit(payload)), sha256);
+            assert_eq!(hex::encode(Keccak256Commitment::commit(payload)), keccak256);
+        }
+
+        // A single byte `2` is absorbed as `Poseidon(1, 2)`, the circomlib
+        // vector for two inputs
+        assert_eq!(
+            hex::encode(PoseidonBn254Commitment::commit(&[2])),
+            "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"
+        );
+        // An empty payload is the initial length
+        assert_eq!(
+            PoseidonBn254Commitment::commit(&[]),
+            RollupCommitment::zero()
+        );
+    }
+
+    #[test]
+    fn test_rollup_commitment_schemes() {
+        let payload = vec![7u8; 100];
+        let commits = [
+            Sha256Commitment::commit(&payload),
+            Keccak256Commitment::commit(&payload),
+            PoseidonBn254Commitment::commit(&payload),
+        ];
+        assert_ne!(commits[0], commits[1]);
+        assert_ne!(commits[0], commits[2]);
+        assert_ne!(commits[1], commits[2]);
+
+        // Dispatching by ID agrees with the schemes
+        assert_eq!(
+            CommitmentSchemeId::Sha256.commit(&payload),
+            Some(commits[0])
+        );
+        assert_eq!(
+            CommitmentSchemeId::Keccak256.commit(&payload),
+            Some(commits[1])
+        );
+        assert_eq!(
+            CommitmentSchemeId::PoseidonBn254.commit(&payload),
+            Some(commits[2])
+        );
+        assert_eq!(CommitmentSchemeId::Eip4844Blob.commit(&payload), None);
+
+        // Trailing zeros change the Poseidon commitment thanks to the length
+        let mut padded = payload.clone();
+        padded.push(0);
+        assert_ne!(PoseidonBn254Commitment::commit(&padded), commits[2]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Consistency among the derivation proof, the rollup's VM execution proof and
+//! the Espresso light client proof, checked by the consistency program after
+//! recursively verifying each of them.
+
+use crate::{
+    abi::{LightClientPublicValuesStruct, LightClientStateStruct, PublicValuesStruct},
+    block::RollupCommitment,
+    commitment::CommitmentSchemeId,
+    light_client::LightClientState,
+};
+use primitive_types::H256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Public inputs expected from a VM execution proof, committed with bincode.
+pub struct VmPublicInputs {
+    /// Scheme used to compute `rollup_txs_commit`
+    pub rollup_commit_scheme: CommitmentSchemeId,
+    /// Commitment to the executed transactions, with the same scheme as the
+    /// derivation proof
+    pub rollup_txs_commit: RollupCommitment,
+    /// Rollup state root before executing the transactions
+    pub prev_state_root: H256,
+    /// Rollup state root after executing the transactions
+    pub new_state_root: H256,
+}
+
+#[derive(Debug)]
+/// Public inputs of the consistency program, committed ABI-encoded, see
+/// [`crate::abi::ConsistencyPublicValuesStruct`]
+pub struct ConsistencyPublicInputs {
+    /// Digest of the verifying key of the derivation program
+    pub derivation_vkey: [u32; 8],
+    /// Digest of the verifying key of the VM execution program
+    pub vm_vkey: [u32; 8],
+    /// Digest of the verifying key of the light client program
+    pub light_client_vkey: [u32; 8],
+    /// Finalized light client state the derivation is checked against
+    pub light_client_state: LightClientState,
+    /// Public values of the derivation relation, as committed by the
+    /// derivation program
+    pub derivation: PublicValuesStruct,
+    /// Rollup state root before executing the derived transactions
+    pub prev_state_root: H256,
+    /// Rollup state root after executing the derived transactions
+    pub new_state_root: H256,
+}
+
+/// Reasons for the sub-proofs to be inconsistent.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsistencyError {
+    #[error("VM proof commits to its transactions with {vm:?}, while the derivation proof uses the scheme of index {derivation}.")]
+    RollupCommitSchemeMismatch {
+        derivation: u8,
+        vm: CommitmentSchemeId,
+    },
+    #[error("VM proof executed transactions committed to {vm:?}, while {derivation:?} are derived from Espresso.")]
+    RollupCommitMismatch {
+        derivation: RollupCommitment,
+        vm: RollupCommitment,
+    },
+    #[error("Derivation proof is checked against the light client state at height {derivation}, not the proven one at height {light_client}.")]
+    LightClientStateMismatch { derivation: u64, light_client: u64 },
+}
+
+/// Checks that the VM executed exactly the derived transactions, and that the
+/// derivation is checked against the proven light client state, returning the
+/// public inputs exactly as committed by the consistency program.
+///
+/// `derivation` are the public values of the derivation proof in light client
+/// mode, as decoded with [`crate::abi::decode_light_client_public_values`].
+pub fn check_consistency(
+    derivation_vkey: [u32; 8],
+    vm_vkey: [u32; 8],
+    light_client_vkey: [u32; 8],
+    derivation: LightClientPublicValuesStruct,
+    vm: VmPublicInputs,
+    light_client_state: LightClientState,
+) -> Result<ConsistencyPublicInputs, ConsistencyError> {
+    let LightClientPublicValuesStruct {
+        lightClientState: derivation_state,
+        derivation,
+    } = derivation;
+    // Equal commitments under different schemes don't commit to the same
+    // transactions
+    if vm.rollup_commit_scheme.to_u8() != derivation.rollupCommitScheme {
+        return Err(ConsistencyError::RollupCommitSchemeMismatch {
+            derivation: derivation.rollupCommitScheme,
+            vm: vm.rollup_commit_scheme,
+        });
+    }
+    if vm.rollup_txs_commit.0 != derivation.rollupTxsCommit.0 {
+        return Err(ConsistencyError::RollupCommitMismatch {
+            derivation: RollupCommitment::from(derivation.rollupTxsCommit.0),
+            vm: vm.rollup_txs_commit,
+        });
+    }
+    if derivation_state != LightClientStateStruct::from(&light_client_state) {
+        return Err(ConsistencyError::LightClientStateMismatch {
+            derivation: derivation_state.blockHeight,
+            light_client: light_client_state.block_height,
+        });
+    }
+
+    Ok(ConsistencyPublicInputs {
+        derivation_vkey,
+        vm_vkey,
+        light_client_vkey,
+        light_client_state,
+        derivation,
+        prev_state_root: vm.prev_state_root,
+        new_state_root: vm.new_state_root,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_consistency, ConsistencyError, VmPublicInputs};
+    use crate::{
+        abi::{
+            decode_consistency_public_values, encode_consistency_public_values,
+            LightClientPublicValuesStruct,
+        },
+        block::header::BlockMerkleTree,
+        commitment::CommitmentSchemeId,
+        light_client::{LightClientPublicInputs, LightClientState},
+        PublicInputs,
+    };
+    use jf_merkle_tree::MerkleTreeScheme;
+    use primitive_types::H256;
+
+    fn derivation(light_client_state: LightClientState) -> LightClientPublicValuesStruct {
+        let bmt_commitment = BlockMerkleTree::new(32).commitment();
+        LightClientPublicValuesStruct::from(&LightClientPublicInputs {
+            light_client_state,
+            derivation: PublicInputs {
+                rollup_commit_scheme: CommitmentSchemeId::Sha256,
+                rollup_txs_commit: H256::repeat_byte(1),
+                vid_param_hash: H256::zero(),
+                ns_id: 42,
+                bmt_commitment,
+                start_height: 0,
+                end_height: 0,
+                prev_batch: None,
+                accumulator: H256::repeat_byte(1),
+            },
+        })
+    }
+
+    #[test]
+    fn test_consistency_checks() {
+        let state = LightClientState::new(1, 0, &BlockMerkleTree::new(32).commitment());
+        let vm = VmPublicInputs {
+            rollup_commit_scheme: CommitmentSchemeId::Sha256,
+            rollup_txs_commit: H256::repeat_byte(1),
+            prev_state_root: H256::repeat_byte(2),
+            new_state_root: H256::repeat_byte(3),
+        };
+        let public_inputs =
+            check_consistency([0; 8], [1; 8], [2; 8], derivation(state), vm.clone(), state)
+                .unwrap();
+        assert_eq!(public_inputs.new_state_root, vm.new_state_root);
+        assert_eq!(public_inputs.vm_vkey, [1; 8]);
+
+        // Committed ABI-encoded
+        let values =
+            decode_consistency_public_values(&encode_consistency_public_values(&public_inputs))
+                .unwrap();
+        assert_eq!(values.vmVkey[..8], [0, 0, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(values.lightClientState.viewNum, 1);
+        assert_eq!(values.derivation.nsId, 42);
+        assert_eq!(values.newStateRoot.0, [3u8; 32]);
+
+        let other_vm = VmPublicInputs {
+            rollup_txs_commit: H256::repeat_byte(4),
+            ..vm.clone()
+        };
+        assert!(matches!(
+            check_consistency([0; 8], [1; 8], [2; 8], derivation(state), other_vm, state),
+            Err(ConsistencyError::RollupCommitMismatch { .. })
+        ));
+
+        // The same commitment under another scheme
+        let other_vm = VmPublicInputs {
+            rollup_commit_scheme: CommitmentSchemeId::Keccak256,
+            ..vm.clone()
+        };
+        assert_eq!(
+            check_consistency([0; 8], [1; 8], [2; 8], derivation(state), other_vm, state)
+                .unwrap_err(),
+            ConsistencyError::RollupCommitSchemeMismatch {
+                derivation: 0,
+                vm: CommitmentSchemeId::Keccak256,
+            }
+        );
+
+        let other_state = LightClientState {
+            view_num: 2,
+            ..state
+        };
+        assert!(matches!(
+            check_consistency([0; 8], [1; 8], [2; 8], derivation(state), vm, other_state),
+            Err(ConsistencyError::LightClientStateMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,545 @@
+//! Verification of derivation proofs, shared by the zkVM programs and native
+//! callers (e.g. batchers checking their inputs before proving).
+
+use crate::{
+    block::{
+        header::{BlockHeader, BlockMerkleCommitment, BlockMerkleTree, BlockMerkleTreeProof},
+        payload::{compute_vid_param_hash, rollup_commit, Payload, VidCommitment, VidParam},
+        RollupCommitment,
+    },
+    commitment::{CommitmentSchemeId, RollupCommitmentScheme, SelectedScheme},
+    ns_table::NsTableValidationError,
+    vid::{self, VidSchemes},
+    BlockDerivationProof, DerivationInputs, PrevBatch, PublicInputs,
+};
+use committable::Committable;
+use jf_merkle_tree::{MerkleCommitment, MerkleTreeScheme};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use std::ops::{Range, RangeInclusive};
+use thiserror::Error;
+
+/// Reasons for a derivation proof to be rejected.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DerivationError {
+    #[error("Incorrect membership proof for block Merkle tree at height {height}.")]
+    BmtMismatch { height: u64 },
+    #[error("Membership proof is not consistent with the block header at height {height}.")]
+    HeaderMismatch { height: u64 },
+    #[error(
+        "Block Merkle tree proof position {pos} is inconsistent with the block height {height}."
+    )]
+    PositionMismatch { height: u64, pos: u64 },
+    #[error("Namespace ID {ns_id} not found in the block at height {height}.")]
+    NamespaceNotFound { height: u64, ns_id: u32 },
+    #[error("Failed namespace proof for the block at height {height}.")]
+    NsProofFailure { height: u64 },
+    #[error("Block at height {height} is dispersed with AvidM, only accepted with the `experimental-avidm` feature.")]
+    ExperimentalAvidM { height: u64 },
+    #[error(
+        "VID common data doesn't match the payload commitment of the block at height {height}."
+    )]
+    VidCommonMismatch { height: u64 },
+    #[error("Malformed namespace table in the block at height {height}: {reason}")]
+    MalformedNsTable {
+        height: u64,
+        reason: NsTableValidationError,
+    },
+    #[error("Payload range {found:?} doesn't start at {expected}.")]
+    RangeGap {
+        expected: usize,
+        found: Range<usize>,
+    },
+    #[error("Payload ranges end at {end}, while the payload has {len} bytes.")]
+    RangeOutOfBounds { end: usize, len: usize },
+    #[error("Block heights are not strictly increasing: {height} after {prev}.")]
+    NonIncreasingHeight { prev: u64, height: u64 },
+    #[error("Gap in block heights, blocks between {prev} and {height} are not covered.")]
+    HeightGap { prev: u64, height: u64 },
+    #[error(
+        "No transaction at index {tx_index} in the namespace of the block at height {height}."
+    )]
+    TxNotFound { height: u64, tx_index: u32 },
+    #[error("Payload of {len} bytes doesn't fit in a blob of {max} bytes.")]
+    BlobTooLarge { len: usize, max: usize },
+    #[error("Block Merkle tree commitment is not the one of the light client state at height {block_height}.")]
+    LightClientMismatch { block_height: u64 },
+    #[error("Stake table doesn't match the stake table commitment.")]
+    StakeTableMismatch,
+    #[error("Quorum certificate is not for the block header at height {height}.")]
+    QcHeaderMismatch { height: u64 },
+    #[error("Invalid quorum certificate signature in view {view_number}.")]
+    InvalidQc { view_number: u64 },
+    #[error("Quorum certificate signed by {signed} stake, below the threshold {threshold}.")]
+    InsufficientStake { signed: U256, threshold: U256 },
+    #[error(
+        "Block Merkle tree commitment is not the one of the finalized header at height {height}."
+    )]
+    FinalizedBmtMismatch { height: u64 },
+    #[error("At least one block is required.")]
+    EmptyBatch,
+    #[error("Batch starting at {start_height} doesn't continue from the previous one ending at {prev_end_height}.")]
+    BatchDiscontinuity {
+        prev_end_height: u64,
+        start_height: u64,
+    },
+}
+
+/// Checks that a block sits at its own height in the block Merkle tree, and
+/// that it immediately follows the previously derived block (if any).
+///
+/// Together these rule out feeding the same Espresso block twice, reordering
+/// blocks, skipping blocks, or pairing a header with a Merkle proof for
+/// another position.
+pub fn check_block_position(
+    prev_height: Option<u64>,
+    block_header: &BlockHeader,
+    bmt_proof: &BlockMerkleTreeProof,
+) -> Result<(), DerivationError> {
+    let height = block_header.height();
+    if bmt_proof.pos != height {
+        return Err(DerivationError::PositionMismatch {
+            height,
+            pos: bmt_proof.pos,
+        });
+    }
+    match prev_height {
+        Some(prev) if prev >= height => Err(DerivationError::NonIncreasingHeight { prev, height }),
+        Some(prev) if prev + 1 != height => Err(DerivationError::HeightGap { prev, height }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a batch starting at `start_height` continues right after
+/// `prev_batch` (if any), and returns the accumulator for this batch.
+pub fn link_prev_batch(
+    prev_batch: Option<&PrevBatch>,
+    start_height: u64,
+    rollup_txs_commit: &RollupCommitment,
+) -> Result<RollupCommitment, DerivationError> {
+    match prev_batch {
+        Some(prev) if prev.end_height + 1 != start_height => {
+            Err(DerivationError::BatchDiscontinuity {
+                prev_end_height: prev.end_height,
+                start_height,
+            })
+        },
+        Some(prev) => Ok(prev.accumulate(rollup_txs_commit)),
+        None => Ok(*rollup_txs_commit),
+    }
+}
+
+/// Verifies the block derivation proof against the public inputs, with VID
+/// schemes shared by the blocks of a batch. ADVZ re-commitments are deferred to
+/// `vid_schemes`, see [`VidSchemes::verify_deferred`].
+pub fn verify_block_derivation_proof(
+    payload_slice: &[u8],
+    vid_schemes: &mut VidSchemes,
+    ns_id: u32,
+    bmt_commitment: &BlockMerkleCommitment,
+    proof: &BlockDerivationProof,
+) -> Result<(), DerivationError> {
+    let height = proof.block_header.height();
+
+    // Check that the membership proof is valid
+    if bmt_commitment.height() + 1 != proof.bmt_proof.proof.len()
+        || !BlockMerkleTree::verify(
+            bmt_commitment.digest(),
+            proof.bmt_proof.pos,
+            &proof.bmt_proof,
+        )
+        .is_ok_and(|result| result.is_ok())
+    {
+        return Err(DerivationError::BmtMismatch { height });
+    }
+    // Check that the header is the one committed in the block Merkle tree
+    if !proof
+        .bmt_proof
+        .elem()
+        .is_some_and(|elem| elem == &proof.block_header.commit())
+    {
+        return Err(DerivationError::HeaderMismatch { height });
+    }
+
+    // AvidM is reimplemented here, without known-answer vectors from Espresso's
+    // `vid` crate yet
+    if !cfg!(feature = "experimental-avidm")
+        && matches!(
+            proof.block_header.payload_commitment(),
+            VidCommitment::V1(_)
+        )
+    {
+        return Err(DerivationError::ExperimentalAvidM { height });
+    }
+
+    let ns_table = proof.block_header.ns_table();
+    // The namespace table is validated against a payload byte length bound by
+    // the payload commitment of the header, not a length taken on trust from
+    // the VID common data.
+    let payload_byte_len = vid::payload_byte_len(
+        proof.block_header.payload_commitment(),
+        &proof.vid_common,
+        ns_table,
+    )
+    .ok_or(DerivationError::VidCommonMismatch { height })?;
+    ns_table
+        .validate(payload_byte_len)
+        .map_err(|reason| DerivationError::MalformedNsTable { height, reason })?;
+    match ns_table.find_ns_id(ns_id) {
+        None => {
+            // The namespace is absent, thus nothing can be derived from this
+            // block.
+            if !payload_slice.is_empty() {
+                return Err(DerivationError::NamespaceNotFound { height, ns_id });
+            }
+        },
+        Some((ns_index, ns_range_start, ns_range_end)) => {
+            let Some(ns_proof) = &proof.ns_proof else {
+                return Err(DerivationError::NsProofFailure { height });
+            };
+            // Namespace proof w.r.t the VidCommitment, with the VID scheme of
+            // this block
+            if !vid::verify_ns_proof(
+                vid_schemes,
+                height,
+                proof.block_header.payload_commitment(),
+                &proof.vid_common,
+                ns_index,
+                ns_range_start as usize..ns_range_end as usize,
+                payload_slice,
+                ns_proof,
+            ) {
+                return Err(DerivationError::NsProofFailure { height });
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Verifies the derivation proofs of a batch of blocks against the rollup's
+/// transactions, returning the range of block heights covered.
+///
+/// 1. Check that the ranges cover the whole payload with no overlapping
+/// 2. Check that block heights are consecutive and consistent with their
+///    positions in the block Merkle tree, so that every block in the height
+///    range is covered exactly once
+/// 3. Check each block derivation proof, then the ADVZ re-commitments of all
+///    blocks at once
+pub fn verify_block_derivation_proofs(
+    rollup_txs: &[u8],
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: &BlockMerkleCommitment,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<RangeInclusive<u64>, DerivationError> {
+    let mut end = 0;
+    let mut prev_height = None;
+    let mut vid_schemes = VidSchemes::new(vid_param);
+    for (range, block_proof) in block_derivation_proofs {
+        if range.start != end {
+            return Err(DerivationError::RangeGap {
+                expected: end,
+                found: range.clone(),
+            });
+        }
+        let payload_slice =
+            rollup_txs
+                .get(range.clone())
+                .ok_or(DerivationError::RangeOutOfBounds {
+                    end: range.end,
+                    len: rollup_txs.len(),
+                })?;
+        check_block_position(
+            prev_height,
+            &block_proof.block_header,
+            &block_proof.bmt_proof,
+        )?;
+        verify_block_derivation_proof(
+            payload_slice,
+            &mut vid_schemes,
+            ns_id,
+            bmt_commitment,
+            block_proof,
+        )?;
+        end = range.end;
+        prev_height = Some(block_proof.block_header.height());
+    }
+    vid_schemes
+        .verify_deferred()
+        .map_err(|height| DerivationError::NsProofFailure { height })?;
+    if end != rollup_txs.len() {
+        return Err(DerivationError::RangeOutOfBounds {
+            end,
+            len: rollup_txs.len(),
+        });
+    }
+
+    match (block_derivation_proofs.first(), prev_height) {
+        (Some((_, first)), Some(end_height)) => Ok(first.block_header.height()..=end_height),
+        _ => Err(DerivationError::EmptyBatch),
+    }
+}
+
+/// Verifies the whole derivation relation natively, returning the public
+/// inputs exactly as committed by the derivation program.
+pub fn verify_derivation(
+    rollup_txs: &Payload,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<PublicInputs, DerivationError> {
+    // Compute the commitment of all the transactions
+    let rollup_txs_commit = rollup_commit(rollup_txs);
+
+    verify_derivation_with_commitment(
+        SelectedScheme::ID,
+        rollup_txs_commit,
+        rollup_txs,
+        vid_param,
+        ns_id,
+        bmt_commitment,
+        prev_batch,
+        block_derivation_proofs,
+    )
+}
+
+/// Same as [`verify_derivation`], for a commitment `rollup_txs_commit` to
+/// `rollup_txs` already computed by the caller with `rollup_commit_scheme`.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_derivation_with_commitment(
+    rollup_commit_scheme: CommitmentSchemeId,
+    rollup_txs_commit: RollupCommitment,
+    rollup_txs: &Payload,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<PublicInputs, DerivationError> {
+    // Verify the Espresso derivation proof
+    let (start_height, end_height) = verify_block_derivation_proofs(
+        &rollup_txs.0,
+        vid_param,
+        ns_id,
+        &bmt_commitment,
+        block_derivation_proofs,
+    )?
+    .into_inner();
+
+    // Link to the previous batch: it must end right before this batch starts
+    let accumulator = link_prev_batch(prev_batch.as_ref(), start_height, &rollup_txs_commit)?;
+
+    Ok(PublicInputs {
+        rollup_commit_scheme,
+        rollup_txs_commit,
+        vid_param_hash: compute_vid_param_hash(vid_param),
+        ns_id,
+        bmt_commitment,
+        start_height,
+        end_height,
+        prev_batch,
+        accumulator,
+    })
+}
+
+impl DerivationInputs {
+    /// Verifies the derivation relation over these inputs natively.
+    pub fn verify(&self) -> Result<PublicInputs, DerivationError> {
+        verify_derivation(
+            &self.rollup_txs,
+            &self.vid_param,
+            self.ns_id,
+            self.bmt_commitment,
+            self.prev_batch,
+            &self.block_derivation_proofs,
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{
+        check_block_position, link_prev_batch, verify_block_derivation_proof, DerivationError,
+    };
+    use crate::{
+        block::{
+            header::{BlockHeader, BlockMerkleTree, BlockMerkleTreeProof},
+            payload::{VidCommon, VidParam},
+        },
+        ns_table::NsTable,
+        vid::{avidm_commit, AvidMCommitment, AvidMCommon, VidSchemes},
+        BlockDerivationProof, PrevBatch,
+    };
+    use ark_bn254::Bn254;
+    use committable::Committable;
+    use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleCommitment, MerkleTreeScheme};
+    use jf_pcs::{prelude::UnivariateKzgPCS, PolynomialCommitmentScheme};
+
+    pub(crate) fn mock_header(height: u64) -> BlockHeader {
+        let mut header: BlockHeader = serde_json::from_str(
+            r#"{
+                "chain_config": {
+                    "chain_config": {
+                        "Left": {
+                            "chain_id": "888888888",
+                            "max_block_size": "30000000",
+                            "base_fee": "0",
+                            "fee_contract": null,
+                            "fee_recipient": "0x0000000000000000000000000000000000000000"
+                        }
+                    }
+                },
+                "height": 0,
+                "timestamp": 1720789795,
+                "l1_head": 5113,
+                "l1_finalized": null,
+                "payload_commitment": "HASH~3XOkaXVZS5e_7xjbbqN22voRnSe_p7Di-U4OPmdCD0JF",
+                "builder_commitment": "BUILDER_COMMITMENT~tEvs0rxqOiMCvfe2R0omNNaphSlUiEDrb2q0IZpRcgA_",
+                "ns_table": {
+                    "bytes": "AAAAAA=="
+                },
+                "block_merkle_tree_root": "MERKLE_COMM~02gWBSt2tcz9XfOOO6xEVicluWIIP95BW8I11f2graggAAAAAAAAAJUQAQAAAAAAUQ",
+                "fee_merkle_tree_root": "MERKLE_COMM~yB4_Aqa35_PoskgTpcCR1oVLh6BUdLHIs7erHKWi-usUAAAAAAAAAAEAAAAAAAAAJg",
+                "fee_info": {
+                    "account": "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f",
+                    "amount": "0"
+                }
+            }"#,
+        )
+        .unwrap();
+        *header.height_mut() = height;
+        header
+    }
+
+    /// Build a block Merkle tree over `num_blocks` mock headers and return the
+    /// headers together with their membership proofs.
+    fn mock_chain(num_blocks: u64) -> Vec<(BlockHeader, BlockMerkleTreeProof)> {
+        let mut bmt = BlockMerkleTree::new(32);
+        let headers: Vec<_> = (0..num_blocks).map(mock_header).collect();
+        headers
+            .iter()
+            .for_each(|header| bmt.push(header.commit()).unwrap());
+        headers
+            .into_iter()
+            .enumerate()
+            .map(|(i, header)| {
+                let (_, proof) = bmt.lookup(i as u64).expect_ok().unwrap();
+                (header, proof)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_position_accepts_consecutive_heights() {
+        let chain = mock_chain(3);
+        let mut prev_height = None;
+        for (header, proof) in chain.iter() {
+            assert_eq!(check_block_position(prev_height, header, proof), Ok(()));
+            prev_height = Some(header.height());
+        }
+    }
+
+    #[test]
+    fn test_block_position_rejects_duplicated_block() {
+        let chain = mock_chain(3);
+        assert_eq!(
+            check_block_position(Some(1), &chain[1].0, &chain[1].1),
+            Err(DerivationError::NonIncreasingHeight { prev: 1, height: 1 })
+        );
+    }
+
+    #[test]
+    fn test_block_position_rejects_reordered_blocks() {
+        let chain = mock_chain(3);
+        assert_eq!(
+            check_block_position(Some(2), &chain[1].0, &chain[1].1),
+            Err(DerivationError::NonIncreasingHeight { prev: 2, height: 1 })
+        );
+    }
+
+    #[test]
+    fn test_block_position_rejects_skipped_block() {
+        let chain = mock_chain(3);
+        assert_eq!(
+            check_block_position(Some(0), &chain[2].0, &chain[2].1),
+            Err(DerivationError::HeightGap { prev: 0, height: 2 })
+        );
+    }
+
+    #[test]
+    fn test_block_position_rejects_mispositioned_block() {
+        let chain = mock_chain(3);
+        assert_eq!(
+            check_block_position(None, &chain[1].0, &chain[2].1),
+            Err(DerivationError::PositionMismatch { height: 1, pos: 2 })
+        );
+    }
+
+    #[test]
+    fn test_link_prev_batch() {
+        let prev = PrevBatch {
+            end_height: 9,
+            accumulator: Default::default(),
+        };
+        let commit = Default::default();
+        assert_eq!(
+            link_prev_batch(Some(&prev), 10, &commit),
+            Ok(prev.accumulate(&commit))
+        );
+        assert_eq!(
+            link_prev_batch(Some(&prev), 9, &commit),
+            Err(DerivationError::BatchDiscontinuity {
+                prev_end_height: 9,
+                start_height: 9
+            })
+        );
+        assert_eq!(link_prev_batch(None, 9, &commit), Ok(commit));
+    }
+
+    #[test]
+    fn test_avidm_blocks_are_experimental() {
+        // A block of one namespace, dispersed with AvidM
+        let common = AvidMCommon {
+            total_weights: 10,
+            recovery_threshold: 4,
+        };
+        let ns_table = NsTable::mock_ns_table(&[(1, 10)]);
+        let tree = avidm_commit(&common, &[0u8; 10], &ns_table).unwrap();
+        let mut block_header = mock_header(0);
+        *block_header.payload_commitment_mut() = AvidMCommitment(tree.commitment().digest()).into();
+        *block_header.ns_table_mut() = ns_table;
+
+        let mut bmt = BlockMerkleTree::new(32);
+        bmt.push(block_header.commit()).unwrap();
+        let (_, bmt_proof) = bmt.lookup(0).expect_ok().unwrap();
+        let proof = BlockDerivationProof {
+            bmt_proof,
+            block_header,
+            vid_common: VidCommon::V1(common),
+            ns_proof: None,
+        };
+        // The SRS is only used by ADVZ
+        let vid_param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut ark_std::test_rng(), 2).unwrap(),
+        );
+
+        // Nothing is derived for another namespace
+        let result = verify_block_derivation_proof(
+            &[],
+            &mut VidSchemes::new(&vid_param),
+            42,
+            &bmt.commitment(),
+            &proof,
+        );
+        if cfg!(feature = "experimental-avidm") {
+            assert_eq!(result, Ok(()));
+        } else {
+            assert_eq!(
+                result,
+                Err(DerivationError::ExperimentalAvidM { height: 0 })
+            );
+        }
+    }
+}
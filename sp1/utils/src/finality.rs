@@ -0,0 +1,499 @@
+//! Finality of an Espresso block checked directly from a quorum certificate
+//! (QC), for chains without an Espresso light client contract.
+//!
+//! The QC is an aggregated BLS signature over BN254 by a subset of the stake
+//! table, holding more than two thirds of the total stake, on the commitment
+//! of a HotShot leaf, which in turn commits to the finalized block header.
+//!
+//! The commitments mirror HotShot's: the leaf, the vote data signed by the
+//! quorum (versioned from the marketplace upgrade on), and the stake table as
+//! committed in the state of the light client contract.
+
+use crate::{
+    block::{
+        header::{BlockHeader, BlockMerkleCommitment, Version},
+        payload::{Payload, VidParam},
+    },
+    derivation::{verify_derivation, DerivationError},
+    BlockDerivationProof, PrevBatch, PublicInputs,
+};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use jf_crhf::CRHF;
+use jf_rescue::crhf::VariableLengthRescueCRHF;
+use jf_signature::{
+    bls_over_bn254::{BLSOverBN254CurveSignatureScheme, Signature, VerKey},
+    AggregateableSignatureSchemes,
+};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Capacity of the stake table of the light client, up to which its
+/// commitment is padded.
+pub const STAKE_TABLE_CAPACITY: usize = 200;
+/// Number of field elements a BLS key is absorbed as in the stake table
+/// commitment.
+pub const BLS_KEY_FIELDS: usize = 3;
+
+/// An entry of the stake table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StakeTableEntry {
+    /// BLS verification key of the node
+    pub stake_key: VerKey,
+    /// Amount of stake delegated to the node
+    pub stake_amount: U256,
+}
+
+/// Commitment to a stake table, as stored in the state of the light client
+/// contract: Rescue hashes of the BLS keys, of the Schnorr keys and of the
+/// stake amounts, each padded with zeros to [`STAKE_TABLE_CAPACITY`] entries.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeTableCommitment {
+    #[serde(with = "jf_utils::canonical")]
+    pub bls_key_comm: Fr,
+    /// The Schnorr keys sign light client states, not QCs, thus this part of
+    /// the commitment isn't checked against the stake table
+    #[serde(with = "jf_utils::canonical")]
+    pub schnorr_key_comm: Fr,
+    #[serde(with = "jf_utils::canonical")]
+    pub amount_comm: Fr,
+}
+
+/// Rescue hash of `elems` padded with zeros to `len` elements, or `None` if
+/// there are more.
+fn padded_rescue_hash(mut elems: Vec<Fr>, len: usize) -> Option<Fr> {
+    if elems.len() > len {
+        return None;
+    }
+    elems.resize(len, Fr::default());
+    Some(VariableLengthRescueCRHF::<Fr, 1>::evaluate(elems).unwrap()[0])
+}
+
+/// Commitments of the BLS keys and of the stake amounts of `stake_table`, or
+/// `None` if it exceeds [`STAKE_TABLE_CAPACITY`].
+///
+/// Field elements are laid out as by HotShot's `ToFields`: a BLS key is
+/// absorbed as [`BLS_KEY_FIELDS`] elements, the 31-byte chunks of its 64
+/// compressed bytes read in little-endian, and a stake amount as one, from its
+/// little-endian bytes reduced modulo the field.
+pub fn stake_table_commitments(stake_table: &[StakeTableEntry]) -> Option<(Fr, Fr)> {
+    let bls_keys = stake_table
+        .iter()
+        .flat_map(|entry| {
+            let mut bytes = Vec::new();
+            entry
+                .stake_key
+                .to_affine()
+                .serialize_compressed(&mut bytes)
+                .unwrap();
+            [
+                Fr::from_le_bytes_mod_order(&bytes[..31]),
+                Fr::from_le_bytes_mod_order(&bytes[31..62]),
+                Fr::from_le_bytes_mod_order(&bytes[62..]),
+            ]
+        })
+        .collect();
+    let amounts = stake_table
+        .iter()
+        .map(|entry| {
+            let mut bytes = [0u8; 32];
+            entry.stake_amount.to_little_endian(&mut bytes);
+            Fr::from_le_bytes_mod_order(&bytes)
+        })
+        .collect();
+    Some((
+        padded_rescue_hash(bls_keys, BLS_KEY_FIELDS * STAKE_TABLE_CAPACITY)?,
+        padded_rescue_hash(amounts, STAKE_TABLE_CAPACITY)?,
+    ))
+}
+
+/// A certificate given by its commitment, which is all a leaf commitment
+/// needs of it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CertificateCommitment(pub [u8; 32]);
+
+impl Committable for CertificateCommitment {
+    fn commit(&self) -> Commitment<Self> {
+        Commitment::from_raw(self.0)
+    }
+
+    fn tag() -> String {
+        "CERTIFICATE".into()
+    }
+}
+
+/// A HotShot leaf, what a quorum actually votes for. Only its block header is
+/// needed in full.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Leaf {
+    /// View number in which the leaf is proposed
+    pub view_number: u64,
+    /// Commitment of the parent leaf
+    pub parent_commitment: Commitment<Leaf>,
+    /// Header of the block of this leaf
+    pub block_header: BlockHeader,
+    /// Commitment of the QC justifying this leaf
+    pub justify_qc: CertificateCommitment,
+    /// Commitment of the upgrade certificate attached to this leaf, if any
+    pub upgrade_certificate: Option<CertificateCommitment>,
+}
+
+impl Committable for Leaf {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("leaf commitment")
+            .u64_field("view number", self.view_number)
+            .field("parent leaf commitment", self.parent_commitment)
+            .field("block header", self.block_header.commit())
+            .field("justify qc", self.justify_qc.commit())
+            .optional("upgrade certificate", &self.upgrade_certificate)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "LEAF".into()
+    }
+}
+
+/// Data a quorum votes for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuorumData {
+    /// Commitment of the leaf voted for
+    pub leaf_commit: Commitment<Leaf>,
+}
+
+impl Committable for QuorumData {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Quorum data")
+            .var_size_bytes(self.leaf_commit.as_ref())
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "QUORUM_DATA".into()
+    }
+}
+
+impl QuorumData {
+    /// The message signed by the quorum in `view_number`, for a leaf of
+    /// `version`: the commitment of the data itself before the marketplace
+    /// upgrade (v0.3), and of the data along with the view number after.
+    pub fn vote_commitment(&self, view_number: u64, version: Version) -> [u8; 32] {
+        if version < Version::V0_3 {
+            self.commit().into()
+        } else {
+            RawCommitmentBuilder::<Self>::new("Vote")
+                .var_size_bytes(self.commit().as_ref())
+                .u64(view_number)
+                .finalize()
+                .into()
+        }
+    }
+}
+
+/// A quorum certificate on [`QuorumData`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QuorumCertificate {
+    pub data: QuorumData,
+    /// View number in which the quorum voted
+    pub view_number: u64,
+    /// Aggregated signature on the vote commitment of `data`, see
+    /// [`QuorumData::vote_commitment`]
+    pub signature: Signature,
+    /// Whether each stake table entry signed, in stake table order
+    pub signers: Vec<bool>,
+}
+
+/// Everything needed to check the finality of the latest block.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FinalityProof {
+    /// Leaf of the finalized block, whose header commits to the block Merkle
+    /// tree
+    pub leaf: Leaf,
+    /// QC finalizing `leaf`
+    pub qc: QuorumCertificate,
+    /// Stake table of the QC's view
+    pub stake_table: Vec<StakeTableEntry>,
+    /// Commitment of the Schnorr keys of the stake table, only to complete
+    /// its [`StakeTableCommitment`]
+    #[serde(with = "jf_utils::canonical")]
+    pub schnorr_key_comm: Fr,
+}
+
+impl FinalityProof {
+    /// Commitment of the stake table, or `None` if it exceeds
+    /// [`STAKE_TABLE_CAPACITY`].
+    pub fn stake_table_comm(&self) -> Option<StakeTableCommitment> {
+        let (bls_key_comm, amount_comm) = stake_table_commitments(&self.stake_table)?;
+        Some(StakeTableCommitment {
+            bls_key_comm,
+            schnorr_key_comm: self.schnorr_key_comm,
+            amount_comm,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Public inputs of the derivation program in finality mode, committed
+/// ABI-encoded, see [`crate::abi::FinalityPublicValuesStruct`]
+pub struct FinalityPublicInputs {
+    /// Commitment of the stake table signing the QC
+    pub stake_table_comm: StakeTableCommitment,
+    /// View number in which the latest block is finalized
+    pub view_number: u64,
+    /// Height of the latest finalized block
+    pub block_height: u64,
+    /// Public inputs of the derivation relation, against the block Merkle
+    /// tree committed in the finalized header
+    pub derivation: PublicInputs,
+}
+
+/// Minimum stake for a QC to be valid: more than two thirds of the total.
+pub fn success_threshold(total_stake: U256) -> U256 {
+    total_stake * 2 / 3 + 1
+}
+
+/// Checks that the leaf of `proof` is finalized by a QC of the stake table
+/// committed in `stake_table_comm`, and that its block header commits to
+/// `bmt_commitment`.
+pub fn verify_finality(
+    stake_table_comm: &StakeTableCommitment,
+    bmt_commitment: &BlockMerkleCommitment,
+    proof: &FinalityProof,
+) -> Result<(), DerivationError> {
+    let block_header = &proof.leaf.block_header;
+    let height = block_header.height();
+    let qc = &proof.qc;
+    // Only the BLS keys and the amounts matter to the QC
+    let (bls_key_comm, amount_comm) =
+        stake_table_commitments(&proof.stake_table).ok_or(DerivationError::StakeTableMismatch)?;
+    if bls_key_comm != stake_table_comm.bls_key_comm || amount_comm != stake_table_comm.amount_comm
+    {
+        return Err(DerivationError::StakeTableMismatch);
+    }
+    if qc.data.leaf_commit != proof.leaf.commit() {
+        return Err(DerivationError::QcHeaderMismatch { height });
+    }
+    if qc.view_number != proof.leaf.view_number || qc.signers.len() != proof.stake_table.len() {
+        return Err(DerivationError::InvalidQc {
+            view_number: qc.view_number,
+        });
+    }
+
+    // Sum the stake of the signers and check it reaches the threshold
+    let (signed_stake, total_stake, signer_keys) = proof.stake_table.iter().zip(&qc.signers).fold(
+        (U256::zero(), U256::zero(), vec![]),
+        |(signed, total, mut keys), (entry, signed_entry)| {
+            if *signed_entry {
+                keys.push(entry.stake_key);
+                (
+                    signed + entry.stake_amount,
+                    total + entry.stake_amount,
+                    keys,
+                )
+            } else {
+                (signed, total + entry.stake_amount, keys)
+            }
+        },
+    );
+    let threshold = success_threshold(total_stake);
+    if signed_stake < threshold {
+        return Err(DerivationError::InsufficientStake {
+            signed: signed_stake,
+            threshold,
+        });
+    }
+
+    // Verify the aggregated signature of the signers
+    BLSOverBN254CurveSignatureScheme::multi_sig_verify(
+        &(),
+        &signer_keys,
+        qc.data
+            .vote_commitment(qc.view_number, block_header.version()),
+        &qc.signature,
+    )
+    .map_err(|_| DerivationError::InvalidQc {
+        view_number: qc.view_number,
+    })?;
+
+    if block_header.block_merkle_tree_root() != bmt_commitment {
+        return Err(DerivationError::FinalizedBmtMismatch { height });
+    }
+    Ok(())
+}
+
+/// Verifies the finality of the latest block, then the derivation relation
+/// against the block Merkle tree it commits to, returning the public inputs
+/// exactly as committed by the derivation program in finality mode.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_finalized_derivation(
+    stake_table_comm: StakeTableCommitment,
+    finality_proof: &FinalityProof,
+    rollup_txs: &Payload,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<FinalityPublicInputs, DerivationError> {
+    verify_finality(&stake_table_comm, &bmt_commitment, finality_proof)?;
+    let derivation = verify_derivation(
+        rollup_txs,
+        vid_param,
+        ns_id,
+        bmt_commitment,
+        prev_batch,
+        block_derivation_proofs,
+    )?;
+    Ok(FinalityPublicInputs {
+        stake_table_comm,
+        view_number: finality_proof.qc.view_number,
+        block_height: finality_proof.leaf.block_header.height(),
+        derivation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        stake_table_commitments, verify_finality, CertificateCommitment, FinalityProof, Leaf,
+        QuorumCertificate, QuorumData, StakeTableCommitment, StakeTableEntry, BLS_KEY_FIELDS,
+        STAKE_TABLE_CAPACITY,
+    };
+    use crate::derivation::{tests::mock_header, DerivationError};
+    use ark_bn254::Fr;
+    use ark_ff::PrimeField;
+    use ark_serialize::CanonicalSerialize;
+    use committable::{Commitment, Committable};
+    use jf_crhf::CRHF;
+    use jf_rescue::crhf::VariableLengthRescueCRHF;
+    use jf_signature::{
+        bls_over_bn254::BLSOverBN254CurveSignatureScheme, AggregateableSignatureSchemes,
+        SignatureScheme,
+    };
+    use primitive_types::U256;
+
+    #[test]
+    fn test_qc_verification() {
+        let mut rng = ark_std::test_rng();
+        let keys: Vec<_> = (0..4)
+            .map(|_| BLSOverBN254CurveSignatureScheme::key_gen(&(), &mut rng).unwrap())
+            .collect();
+        let stake_table: Vec<_> = keys
+            .iter()
+            .map(|(_, stake_key)| StakeTableEntry {
+                stake_key: *stake_key,
+                stake_amount: U256::from(10),
+            })
+            .collect();
+        let (bls_key_comm, amount_comm) = stake_table_commitments(&stake_table).unwrap();
+        let stake_table_comm = StakeTableCommitment {
+            bls_key_comm,
+            schnorr_key_comm: Fr::from(1u64),
+            amount_comm,
+        };
+
+        // A key is absorbed as the 31-byte chunks of its compressed bytes
+        let mut bytes = Vec::new();
+        stake_table[0]
+            .stake_key
+            .to_affine()
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes.len(), 64);
+        let mut elems: Vec<Fr> = bytes.chunks(31).map(Fr::from_le_bytes_mod_order).collect();
+        assert_eq!(elems.len(), BLS_KEY_FIELDS);
+        elems.resize(BLS_KEY_FIELDS * STAKE_TABLE_CAPACITY, Fr::from(0u64));
+        assert_eq!(
+            stake_table_commitments(&stake_table[..1]).unwrap().0,
+            VariableLengthRescueCRHF::<Fr, 1>::evaluate(elems).unwrap()[0]
+        );
+
+        let leaf = Leaf {
+            view_number: 7,
+            parent_commitment: Commitment::from_raw([1; 32]),
+            block_header: mock_header(5),
+            justify_qc: CertificateCommitment([2; 32]),
+            upgrade_certificate: None,
+        };
+        let bmt_commitment = *leaf.block_header.block_merkle_tree_root();
+        let data = QuorumData {
+            leaf_commit: leaf.commit(),
+        };
+        let vote = data.vote_commitment(7, leaf.block_header.version());
+        // Sign with the first `num_signers` nodes
+        let qc = |num_signers: usize| {
+            let (sks, vks): (Vec<_>, Vec<_>) = keys[..num_signers].iter().cloned().unzip();
+            let sigs: Vec<_> = sks
+                .iter()
+                .map(|sk| {
+                    BLSOverBN254CurveSignatureScheme::sign(&(), sk, vote, &mut ark_std::test_rng())
+                        .unwrap()
+                })
+                .collect();
+            QuorumCertificate {
+                data,
+                view_number: 7,
+                signature: BLSOverBN254CurveSignatureScheme::aggregate(&(), &vks, &sigs).unwrap(),
+                signers: (0..keys.len()).map(|i| i < num_signers).collect(),
+            }
+        };
+        let mut proof = FinalityProof {
+            leaf,
+            qc: qc(3),
+            stake_table,
+            schnorr_key_comm: Fr::from(1u64),
+        };
+        assert_eq!(proof.stake_table_comm(), Some(stake_table_comm));
+        assert!(verify_finality(&stake_table_comm, &bmt_commitment, &proof).is_ok());
+
+        // Wrong stake table
+        let wrong_comm = StakeTableCommitment {
+            amount_comm: Fr::from(0u64),
+            ..stake_table_comm
+        };
+        assert_eq!(
+            verify_finality(&wrong_comm, &bmt_commitment, &proof).unwrap_err(),
+            DerivationError::StakeTableMismatch
+        );
+
+        // Claiming a signer that didn't sign
+        proof.qc.signers[3] = true;
+        assert_eq!(
+            verify_finality(&stake_table_comm, &bmt_commitment, &proof).unwrap_err(),
+            DerivationError::InvalidQc { view_number: 7 }
+        );
+
+        // Two signers out of four are not enough
+        proof.qc = qc(2);
+        assert_eq!(
+            verify_finality(&stake_table_comm, &bmt_commitment, &proof).unwrap_err(),
+            DerivationError::InsufficientStake {
+                signed: U256::from(20),
+                threshold: U256::from(27),
+            }
+        );
+
+        // Votes of another view
+        proof.qc = qc(3);
+        proof.qc.view_number = 8;
+        assert_eq!(
+            verify_finality(&stake_table_comm, &bmt_commitment, &proof).unwrap_err(),
+            DerivationError::InvalidQc { view_number: 8 }
+        );
+
+        // QC for another header
+        proof.qc = qc(3);
+        *proof.leaf.block_header.height_mut() = 6;
+        assert_eq!(
+            verify_finality(&stake_table_comm, &bmt_commitment, &proof).unwrap_err(),
+            DerivationError::QcHeaderMismatch { height: 6 }
+        );
+
+        // Stake table over the capacity of the light client
+        proof.stake_table = vec![proof.stake_table[0].clone(); STAKE_TABLE_CAPACITY + 1];
+        assert_eq!(proof.stake_table_comm(), None);
+    }
+}
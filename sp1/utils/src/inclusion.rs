@@ -0,0 +1,159 @@
+//! Inclusion of a single rollup transaction in an Espresso block, and
+//! non-inclusion of a transaction in a range of blocks.
+
+use crate::{
+    block::{
+        header::BlockMerkleCommitment,
+        payload::{compute_vid_param_hash, Payload, VidParam},
+    },
+    derivation::{
+        check_block_position, verify_block_derivation_proof, verify_derivation, DerivationError,
+    },
+    ns_payload::NsPayload,
+    vid::VidSchemes,
+    BlockDerivationProof, DerivationInputs, PrevBatch, PublicInputs,
+};
+use primitive_types::H256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Public inputs of a transaction inclusion proof
+pub struct InclusionPublicInputs {
+    /// Block Merkle tree commitment the block is checked against
+    pub bmt_commitment: BlockMerkleCommitment,
+    /// Hash of the used VID public parameter
+    pub vid_param_hash: H256,
+    /// Namespace ID of the rollup
+    pub ns_id: u32,
+    /// Height of the block including the transaction
+    pub height: u64,
+    /// Index of the transaction in the rollup's namespace of this block
+    pub tx_index: u32,
+    /// Hash of the transaction bytes, see [`tx_hash`]
+    pub tx_hash: H256,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Proves that a namespace payload, thus all of its transactions, is the one
+/// of an Espresso block.
+pub struct TxInclusionProof {
+    /// Complete bytes of the rollup's namespace in the block
+    pub ns_payload: Vec<u8>,
+    /// Derivation proof of `ns_payload` from the block
+    pub block_proof: BlockDerivationProof,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Public inputs of a transaction non-inclusion proof, committed ABI-encoded,
+/// see [`crate::abi::NonInclusionPublicValuesStruct`]
+pub struct NonInclusionPublicInputs {
+    /// Public inputs of the derivation relation, establishing that the
+    /// complete namespace of every block in `start_height..=end_height` was
+    /// scanned
+    pub derivation: PublicInputs,
+    /// Hash of the transaction looked for, see [`tx_hash`]
+    pub tx_hash: H256,
+    /// Whether no transaction in the scanned blocks has hash `tx_hash`
+    pub not_included: bool,
+}
+
+/// Hash of a rollup transaction.
+pub fn tx_hash(tx: &[u8]) -> H256 {
+    let bytes: [u8; 32] = Sha256::digest(tx).into();
+    bytes.into()
+}
+
+/// Verifies that the transaction at `tx_index` in namespace `ns_id` is
+/// included in a block committed in `bmt_commitment`, returning the public
+/// inputs exactly as committed by the inclusion program.
+pub fn verify_tx_inclusion(
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    tx_index: u32,
+    proof: &TxInclusionProof,
+) -> Result<InclusionPublicInputs, DerivationError> {
+    let block_proof = &proof.block_proof;
+    let height = block_proof.block_header.height();
+    check_block_position(None, &block_proof.block_header, &block_proof.bmt_proof)?;
+    let mut vid_schemes = VidSchemes::new(vid_param);
+    verify_block_derivation_proof(
+        &proof.ns_payload,
+        &mut vid_schemes,
+        ns_id,
+        &bmt_commitment,
+        block_proof,
+    )?;
+    vid_schemes
+        .verify_deferred()
+        .map_err(|height| DerivationError::NsProofFailure { height })?;
+
+    let tx = NsPayload(&proof.ns_payload)
+        .tx(tx_index)
+        .ok_or(DerivationError::TxNotFound { height, tx_index })?;
+
+    Ok(InclusionPublicInputs {
+        bmt_commitment,
+        vid_param_hash: compute_vid_param_hash(vid_param),
+        ns_id,
+        height,
+        tx_index,
+        tx_hash: tx_hash(tx),
+    })
+}
+
+/// Verifies the derivation relation, then scans every transaction of the
+/// derived namespace payloads for one with hash `tx_hash`, returning the
+/// public inputs exactly as committed by the derivation program in
+/// non-inclusion mode.
+pub fn verify_non_inclusion(
+    rollup_txs: &Payload,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+    tx_hash: H256,
+) -> Result<NonInclusionPublicInputs, DerivationError> {
+    let derivation = verify_derivation(
+        rollup_txs,
+        vid_param,
+        ns_id,
+        bmt_commitment,
+        prev_batch,
+        block_derivation_proofs,
+    )?;
+
+    // Ranges are within `rollup_txs` once the derivation is verified
+    let not_included = block_derivation_proofs
+        .iter()
+        .flat_map(|(range, _)| NsPayload(&rollup_txs.0[range.clone()]).txs())
+        .all(|tx| self::tx_hash(tx) != tx_hash);
+
+    Ok(NonInclusionPublicInputs {
+        derivation,
+        tx_hash,
+        not_included,
+    })
+}
+
+impl DerivationInputs {
+    /// Verifies natively that no transaction with hash `tx_hash` is derived
+    /// from these inputs.
+    pub fn verify_non_inclusion(
+        &self,
+        tx_hash: H256,
+    ) -> Result<NonInclusionPublicInputs, DerivationError> {
+        verify_non_inclusion(
+            &self.rollup_txs,
+            &self.vid_param,
+            self.ns_id,
+            self.bmt_commitment,
+            self.prev_batch,
+            &self.block_derivation_proofs,
+            tx_hash,
+        )
+    }
+}
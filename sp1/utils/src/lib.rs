@@ -5,18 +5,34 @@
 
 use block::{
     header::{BlockHeader, BlockMerkleCommitment, BlockMerkleTreeProof},
-    payload::{NsProof, VidCommon},
+    payload::{NsProof, Payload, VidCommon, VidParam},
     RollupCommitment,
 };
+use commitment::CommitmentSchemeId;
+use ns_payload::NsPayload;
 use primitive_types::H256;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
 
+pub mod abi;
+pub mod blob;
 pub mod block;
+pub mod commitment;
+pub mod consistency;
+pub mod derivation;
+pub mod finality;
+pub mod inclusion;
+pub mod light_client;
+pub mod ns_payload;
 pub mod ns_table;
+pub mod vid;
 
 #[derive(Serialize, Deserialize, Debug)]
 /// Public inputs
 pub struct PublicInputs {
+    /// Scheme used to compute `rollup_txs_commit`
+    pub rollup_commit_scheme: CommitmentSchemeId,
     pub rollup_txs_commit: RollupCommitment,
     /// Hash of the used VID public parameter
     pub vid_param_hash: H256,
@@ -25,6 +41,92 @@ pub struct PublicInputs {
     /// Block Merkle tree commitment. Block MT contains information about all
     /// historical blocks up to some block height.
     pub bmt_commitment: BlockMerkleCommitment,
+    /// Height of the first Espresso block covered by this derivation
+    pub start_height: u64,
+    /// Height of the last Espresso block covered by this derivation
+    /// (inclusive). Every block in `start_height..=end_height` is covered
+    /// exactly once.
+    pub end_height: u64,
+    /// Linkage to the previous batch, `None` if this batch isn't chained
+    pub prev_batch: Option<PrevBatch>,
+    /// Running accumulator over `rollup_txs_commit` of all chained batches
+    /// up to this one. Equals `rollup_txs_commit` if `prev_batch` is `None`.
+    pub accumulator: RollupCommitment,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// Where the previous batch of a chained derivation proof ended.
+///
+/// A verifier only needs to store the `end_height` and `accumulator` from the
+/// latest proof to check that the next one continues from it with no gaps or
+/// overlaps.
+pub struct PrevBatch {
+    /// Height of the last Espresso block processed by the previous batch
+    pub end_height: u64,
+    /// Accumulator output by the previous batch. For a batch following an
+    /// unchained one, this is simply the previous `rollup_txs_commit`.
+    pub accumulator: RollupCommitment,
+}
+
+impl PrevBatch {
+    /// Fold the commitment of the current batch into the running accumulator.
+    pub fn accumulate(&self, rollup_txs_commit: &RollupCommitment) -> RollupCommitment {
+        let bytes: [u8; 32] = Sha256::new()
+            .chain_update(self.accumulator)
+            .chain_update(rollup_txs_commit)
+            .finalize()
+            .into();
+        bytes.into()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// What the derivation program proves about its inputs.
+pub enum DerivationMode {
+    /// Commit the [`PublicInputs`] of the derivation relation
+    Derivation,
+    /// Additionally scan all derived transactions for one with hash
+    /// `tx_hash`, and commit the
+    /// [`inclusion::NonInclusionPublicInputs`]
+    NonInclusion { tx_hash: H256 },
+    /// Commit to the transactions with the versioned hash of the EIP-4844
+    /// blob holding them, reading the [`blob::BlobSetup`] as an extra input,
+    /// and commit the [`blob::BlobPublicInputs`]
+    Blob,
+    /// Check that the block Merkle tree commitment is the one committed in
+    /// this light client state, and commit the
+    /// [`light_client::LightClientPublicInputs`]
+    LightClient {
+        state: light_client::LightClientState,
+    },
+    /// Check that the block Merkle tree commitment is the one of a block
+    /// header finalized by a quorum certificate of the stake table committed
+    /// in `stake_table_comm`, reading the [`finality::FinalityProof`] as an
+    /// extra input, and commit the [`finality::FinalityPublicInputs`]
+    Finality {
+        stake_table_comm: finality::StakeTableCommitment,
+    },
+}
+
+impl DerivationMode {
+    pub const DERIVATION_ID: u8 = 0;
+    pub const NON_INCLUSION_ID: u8 = 1;
+    pub const BLOB_ID: u8 = 2;
+    pub const LIGHT_CLIENT_ID: u8 = 3;
+    pub const FINALITY_ID: u8 = 4;
+
+    /// Discriminator of the mode, committed first by the derivation program in
+    /// every mode so that the public values of a mode can't be passed off as
+    /// those of another.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Derivation => Self::DERIVATION_ID,
+            Self::NonInclusion { .. } => Self::NON_INCLUSION_ID,
+            Self::Blob => Self::BLOB_ID,
+            Self::LightClient { .. } => Self::LIGHT_CLIENT_ID,
+            Self::Finality { .. } => Self::FINALITY_ID,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -35,8 +137,69 @@ pub struct BlockDerivationProof {
     /// Block header
     pub block_header: BlockHeader,
     /// Common data associated with the VID disperser, used for namespace proof
-    /// verification
+    /// verification. Tagged with the VID scheme, which must be the one of the
+    /// header's payload commitment.
     pub vid_common: VidCommon,
-    /// Namespace proof of the given payload
-    pub ns_proof: NsProof,
+    /// Namespace proof of the given payload, tagged with the VID scheme.
+    /// `None` if and only if the rollup's namespace is absent from this block,
+    /// in which case the derived payload slice is empty.
+    pub ns_proof: Option<NsProof>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// All inputs to the derivation program, e.g. for saving a batch to disk and
+/// checking it natively before proving.
+pub struct DerivationInputs {
+    /// All transactions of the rollup in bytes form
+    pub rollup_txs: Payload,
+    /// VID public parameter for checking the namespace proofs
+    pub vid_param: VidParam,
+    /// Namespace ID of the rollup
+    pub ns_id: u32,
+    /// Block Merkle tree commitment that all blocks are checked against
+    pub bmt_commitment: BlockMerkleCommitment,
+    /// Where the previous batch ended, if this batch is chained to it
+    pub prev_batch: Option<PrevBatch>,
+    /// A pair of `(range, proof)` for each block
+    pub block_derivation_proofs: Vec<(Range<usize>, BlockDerivationProof)>,
+}
+
+impl DerivationInputs {
+    /// Iterate over the rollup's transactions, parsed from the namespace
+    /// payload of each block in order. Ranges out of the payload bounds, which
+    /// [`DerivationInputs::verify`] rejects, yield no transaction.
+    pub fn transactions(&self) -> impl Iterator<Item = &[u8]> {
+        self.block_derivation_proofs.iter().flat_map(|(range, _)| {
+            NsPayload(self.rollup_txs.0.get(range.clone()).unwrap_or_default()).txs()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrevBatch;
+    use crate::block::{
+        payload::{rollup_commit, Payload},
+        RollupCommitment,
+    };
+
+    #[test]
+    fn test_batch_accumulator_is_order_dependent() {
+        fn fold(commits: &[RollupCommitment]) -> RollupCommitment {
+            commits[1..].iter().fold(commits[0], |acc, commit| {
+                PrevBatch {
+                    end_height: 0,
+                    accumulator: acc,
+                }
+                .accumulate(commit)
+            })
+        }
+
+        let commits: Vec<_> = (0u8..3)
+            .map(|i| rollup_commit(&Payload(vec![i; 8])))
+            .collect();
+        let acc = fold(&commits);
+        assert_ne!(acc, fold(&[commits[1], commits[0], commits[2]]));
+        assert_ne!(acc, fold(&commits[..2]));
+    }
 }
@@ -0,0 +1,176 @@
+//! Binding of the block Merkle tree commitment to the state of the Espresso
+//! light client contract, so that a derivation proof can be checked against
+//! the finalized state on L1 instead of an opaque `bmt_commitment`.
+
+use crate::{
+    block::{
+        header::BlockMerkleCommitment,
+        payload::{Payload, VidParam},
+    },
+    derivation::{verify_derivation, DerivationError},
+    BlockDerivationProof, PrevBatch, PublicInputs,
+};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use jf_crhf::CRHF;
+use jf_merkle_tree::MerkleCommitment;
+use jf_rescue::crhf::VariableLengthRescueCRHF;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, str::FromStr};
+
+/// Finalized state stored by the Espresso light client contract, with the
+/// fields of its `LightClientState`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightClientState {
+    /// View number of the finalized block
+    pub view_num: u64,
+    /// Height of the finalized block
+    pub block_height: u64,
+    /// Block Merkle tree commitment of the finalized block, as a field
+    /// element, see [`block_comm_root`]
+    #[serde(with = "jf_utils::canonical")]
+    pub block_comm_root: Fr,
+}
+
+impl LightClientState {
+    /// The state of the block at `block_height`, whose header commits to
+    /// `bmt_commitment`.
+    pub fn new(view_num: u64, block_height: u64, bmt_commitment: &BlockMerkleCommitment) -> Self {
+        Self {
+            view_num,
+            block_height,
+            block_comm_root: block_comm_root(bmt_commitment),
+        }
+    }
+
+    /// The state as read from the light client contract, with `block_comm_root`
+    /// the decimal `uint256` it stores.
+    ///
+    /// Return `None` if `block_comm_root` isn't a field element in decimal.
+    pub fn from_contract(view_num: u64, block_height: u64, block_comm_root: &str) -> Option<Self> {
+        Some(Self {
+            view_num,
+            block_height,
+            block_comm_root: Fr::from_str(block_comm_root).ok()?,
+        })
+    }
+
+    /// Checks that `bmt_commitment` is the one committed in this state.
+    ///
+    /// The header at `block_height` commits to the tree of all blocks before
+    /// it, thus the tree must have exactly `block_height` leaves.
+    pub fn check_bmt_commitment(
+        &self,
+        bmt_commitment: &BlockMerkleCommitment,
+    ) -> Result<(), DerivationError> {
+        if bmt_commitment.size() != self.block_height
+            || block_comm_root(bmt_commitment) != self.block_comm_root
+        {
+            return Err(DerivationError::LightClientMismatch {
+                block_height: self.block_height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The field element representing a block Merkle tree commitment in the light
+/// client state, computed the same way as by Espresso nodes with
+/// `hash_bytes_to_field` of `hotshot-types`: the compressed commitment bytes
+/// are split into 31-byte little-endian chunks, each fitting in a field
+/// element, which are hashed with Rescue.
+///
+/// This conversion is one-way, the commitment itself has to be supplied to
+/// recover it from a light client state.
+pub fn block_comm_root(bmt_commitment: &BlockMerkleCommitment) -> Fr {
+    let mut bytes = Vec::new();
+    bmt_commitment.serialize_compressed(&mut bytes).unwrap();
+    // Chunks of one byte less than the field size, so that no reduction
+    // happens
+    let chunk_byte_len = (Fr::MODULUS_BIT_SIZE as usize).div_ceil(8) - 1;
+    let elems: Vec<_> = bytes
+        .chunks(chunk_byte_len)
+        .map(Fr::from_le_bytes_mod_order)
+        .collect();
+    VariableLengthRescueCRHF::<Fr, 1>::evaluate(elems).unwrap()[0]
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Public inputs of the derivation program in light client mode, committed
+/// ABI-encoded, see [`crate::abi::LightClientPublicValuesStruct`]
+pub struct LightClientPublicInputs {
+    /// Light client state committing to `derivation.bmt_commitment`
+    pub light_client_state: LightClientState,
+    /// Public inputs of the derivation relation
+    pub derivation: PublicInputs,
+}
+
+/// Verifies that `bmt_commitment` is committed in `light_client_state`, then
+/// the derivation relation against it, returning the public inputs exactly
+/// as committed by the derivation program in light client mode.
+pub fn verify_light_client_derivation(
+    light_client_state: LightClientState,
+    rollup_txs: &Payload,
+    vid_param: &VidParam,
+    ns_id: u32,
+    bmt_commitment: BlockMerkleCommitment,
+    prev_batch: Option<PrevBatch>,
+    block_derivation_proofs: &[(Range<usize>, BlockDerivationProof)],
+) -> Result<LightClientPublicInputs, DerivationError> {
+    light_client_state.check_bmt_commitment(&bmt_commitment)?;
+    let derivation = verify_derivation(
+        rollup_txs,
+        vid_param,
+        ns_id,
+        bmt_commitment,
+        prev_batch,
+        block_derivation_proofs,
+    )?;
+    Ok(LightClientPublicInputs {
+        light_client_state,
+        derivation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LightClientState;
+    use crate::{block::header::BlockMerkleTree, derivation::DerivationError};
+    use committable::Commitment;
+    use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleTreeScheme};
+
+    #[test]
+    fn test_light_client_state_binding() {
+        let mut bmt = BlockMerkleTree::new(32);
+        bmt.push(Commitment::from_raw([1u8; 32])).unwrap();
+        bmt.push(Commitment::from_raw([2u8; 32])).unwrap();
+        let bmt_commitment = bmt.commitment();
+
+        let state = LightClientState::new(7, 2, &bmt_commitment);
+        assert!(state.check_bmt_commitment(&bmt_commitment).is_ok());
+        let state: LightClientState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert!(state.check_bmt_commitment(&bmt_commitment).is_ok());
+
+        // As read from the contract
+        let root = state.block_comm_root.to_string();
+        assert_eq!(LightClientState::from_contract(7, 2, &root), Some(state));
+        assert_eq!(LightClientState::from_contract(7, 2, "0x12"), None);
+
+        // Another tree, or the same tree at another height, is rejected
+        let err = DerivationError::LightClientMismatch { block_height: 2 };
+        let mut other = BlockMerkleTree::new(32);
+        other.push(Commitment::from_raw([1u8; 32])).unwrap();
+        other.push(Commitment::from_raw([3u8; 32])).unwrap();
+        assert_eq!(
+            state.check_bmt_commitment(&other.commitment()).unwrap_err(),
+            err
+        );
+        let state = LightClientState {
+            block_height: 3,
+            ..state
+        };
+        assert!(state.check_bmt_commitment(&bmt_commitment).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Definitions and utilities for the payload of a namespace in an espresso
+//! block. Parsing rules are "unwrapped" from espresso-sequencer repo.
+use std::ops::Range;
+
+/// Byte lengths for the different items that could appear in a transaction
+/// table.
+const NUM_TXS_BYTE_LEN: usize = 4;
+const TX_OFFSET_BYTE_LEN: usize = 4;
+
+/// Bytes of a namespace in a block payload.
+///
+/// The namespace starts with a transaction table, a little-endian `u32`
+/// header declaring the number of transactions followed by the `u32` end
+/// offset of each transaction, then the transaction bodies. Offsets are
+/// relative to the end of the table.
+///
+/// Any byte string is a namespace payload: a malformed table is read the same
+/// way as in espresso-sequencer, without failing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NsPayload<'a>(pub &'a [u8]);
+
+impl<'a> NsPayload<'a> {
+    /// Build a namespace payload containing `txs`.
+    pub fn build<T: AsRef<[u8]>>(txs: &[T]) -> Vec<u8> {
+        let tx_table_byte_len = NUM_TXS_BYTE_LEN + txs.len() * TX_OFFSET_BYTE_LEN;
+        let mut bytes = Vec::with_capacity(
+            tx_table_byte_len + txs.iter().map(|tx| tx.as_ref().len()).sum::<usize>(),
+        );
+        bytes.extend_from_slice(&(txs.len() as u32).to_le_bytes());
+        let mut offset = 0usize;
+        for tx in txs {
+            offset += tx.as_ref().len();
+            bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+        }
+        for tx in txs {
+            bytes.extend_from_slice(tx.as_ref());
+        }
+        bytes
+    }
+
+    /// Number of transactions in this namespace.
+    ///
+    /// Defined as the number of transactions declared in the table header,
+    /// capped by the maximum number of offsets that could fit in the payload.
+    /// A truncated header is zero-padded.
+    pub fn num_txs(&self) -> u32 {
+        let mut header = [0u8; NUM_TXS_BYTE_LEN];
+        let header_len = self.0.len().min(NUM_TXS_BYTE_LEN);
+        header[..header_len].copy_from_slice(&self.0[..header_len]);
+        let max_num_txs = self.0.len().saturating_sub(NUM_TXS_BYTE_LEN) / TX_OFFSET_BYTE_LEN;
+        std::cmp::min(u32::from_le_bytes(header), max_num_txs as u32)
+    }
+
+    /// Byte range of the transaction at `index` in this namespace payload, or
+    /// `None` if there's no such transaction.
+    ///
+    /// Offsets are clamped to the end of the payload, and a transaction
+    /// starting after its end is empty.
+    pub fn tx_range(&self, index: u32) -> Option<Range<usize>> {
+        let num_txs = self.num_txs();
+        if index >= num_txs {
+            return None;
+        }
+        let tx_table_byte_len = NUM_TXS_BYTE_LEN + num_txs as usize * TX_OFFSET_BYTE_LEN;
+        let read_offset = |index: u32| {
+            let pos = NUM_TXS_BYTE_LEN + index as usize * TX_OFFSET_BYTE_LEN;
+            u32::from_le_bytes(self.0[pos..pos + TX_OFFSET_BYTE_LEN].try_into().unwrap()) as usize
+        };
+
+        let end = read_offset(index)
+            .saturating_add(tx_table_byte_len)
+            .min(self.0.len());
+        let start = if index == 0 {
+            0
+        } else {
+            read_offset(index - 1)
+        }
+        .saturating_add(tx_table_byte_len)
+        .min(end);
+        Some(start..end)
+    }
+
+    /// The transaction at `index` in this namespace, or `None` if there's no
+    /// such transaction.
+    pub fn tx(&self, index: u32) -> Option<&'a [u8]> {
+        let bytes = self.0;
+        self.tx_range(index).map(|range| &bytes[range])
+    }
+
+    /// Iterate over all transactions in this namespace.
+    pub fn txs(self) -> impl Iterator<Item = &'a [u8]> {
+        (0..self.num_txs()).map(move |index| self.tx(index).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NsPayload;
+
+    fn txs_of(bytes: &[u8]) -> Vec<&[u8]> {
+        NsPayload(bytes).txs().collect()
+    }
+
+    #[test]
+    fn test_ns_payload_round_trip() {
+        let txs = [&b"first"[..], &b""[..], &b"third transaction"[..]];
+        let bytes = NsPayload::build(&txs);
+        assert_eq!(NsPayload(&bytes).num_txs(), 3);
+        assert_eq!(txs_of(&bytes), txs);
+        assert_eq!(NsPayload(&bytes).tx(3), None);
+
+        assert!(txs_of(&NsPayload::build::<&[u8]>(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_ns_payload_malformed_tx_table() {
+        // Truncated header
+        assert_eq!(NsPayload(&[]).num_txs(), 0);
+        assert_eq!(NsPayload(&[5, 0]).num_txs(), 0);
+
+        // Header declares more transactions than there are offsets
+        let mut bytes = NsPayload::build(&[b"abc", b"def"]);
+        bytes[..4].copy_from_slice(&100u32.to_le_bytes());
+        // Offsets and bodies make 14 bytes, thus at most 3 offsets fit, of
+        // which the third one is made of body bytes
+        assert_eq!(NsPayload(&bytes).num_txs(), 3);
+
+        // Offsets past the end of the payload are clamped
+        let mut bytes = NsPayload::build(&[b"abc", b"def"]);
+        bytes[8..12].copy_from_slice(&100u32.to_le_bytes());
+        assert_eq!(txs_of(&bytes), [&b"abc"[..], &b"def"[..]]);
+
+        // Decreasing offsets yield an empty transaction
+        let mut bytes = NsPayload::build(&[b"abc", b"def"]);
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(txs_of(&bytes), [&b"abc"[..], &b""[..]]);
+    }
+}
@@ -2,6 +2,7 @@
 //! Most of contents are "unwrapped" from espresso-sequencer repo.
 use committable::{Commitment, Committable, RawCommitmentBuilder};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// Byte lengths for the different items that could appear in a namespace table.
 const NUM_NSS_BYTE_LEN: usize = 4;
@@ -10,7 +11,27 @@ const NS_OFFSET_BYTE_LEN: usize = 4;
 // Byte length for namespace IDs.
 const NS_ID_BYTE_LEN: usize = 4;
 
+/// Reasons for a namespace table to be invalid, following espresso-sequencer.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NsTableValidationError {
+    #[error("Byte length doesn't fit a whole number of entries.")]
+    InvalidByteLen,
+    #[error("Header doesn't declare the number of entries in the table.")]
+    InvalidHeader,
+    #[error("Namespace IDs and offsets are not strictly increasing.")]
+    NonIncreasingEntries,
+    #[error("Empty namespace table for a non-empty payload.")]
+    ExpectNonemptyNsTable,
+    #[error("Final offset doesn't match the payload byte length.")]
+    InvalidFinalOffset,
+}
+
 /// Type definition for a namespace table.
+///
+/// The table is a little-endian `u32` header declaring the number of entries,
+/// followed by entries of a `u32` namespace ID and the `u32` end offset of
+/// this namespace in the payload. Each namespace starts where the previous one
+/// ends, the first one at 0.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NsTable {
     #[serde(with = "base64_bytes")]
@@ -20,11 +41,63 @@ pub struct NsTable {
 impl NsTable {
     /// Number of entries in the namespace table.
     ///
-    /// Defined as the maximum number of entries that could fit in the namespace
-    /// table, ignoring what's declared in the table header.
+    /// Defined as the number of entries declared in the table header, capped
+    /// by the maximum number of entries that could fit in the namespace table.
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> u32 {
-        u32::from_le_bytes(self.bytes[..NUM_NSS_BYTE_LEN].try_into().unwrap())
+        std::cmp::min(self.read_num_nss(), self.max_num_nss())
+    }
+
+    /// Check the namespace table against the byte length of its payload.
+    ///
+    /// A valid table has exactly as many entries as declared in its header,
+    /// strictly increasing namespace IDs (thus no duplicates), strictly
+    /// increasing offsets, and ends exactly at the end of the payload. An empty
+    /// table is only valid for an empty payload.
+    pub fn validate(&self, payload_byte_len: usize) -> Result<(), NsTableValidationError> {
+        use NsTableValidationError::*;
+
+        if self.bytes.len() < NUM_NSS_BYTE_LEN
+            || (self.bytes.len() - NUM_NSS_BYTE_LEN) % (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN) != 0
+        {
+            return Err(InvalidByteLen);
+        }
+        if self.read_num_nss() != self.max_num_nss() {
+            return Err(InvalidHeader);
+        }
+
+        let mut prev_id = None;
+        let mut prev_offset = 0u32;
+        for index in 0..self.len() {
+            let (id, _, offset) = self.read_unchecked(index);
+            if prev_id.is_some_and(|prev_id| id <= prev_id) || offset <= prev_offset {
+                return Err(NonIncreasingEntries);
+            }
+            (prev_id, prev_offset) = (Some(id), offset);
+        }
+
+        if self.len() == 0 && payload_byte_len != 0 {
+            return Err(ExpectNonemptyNsTable);
+        }
+        if self.len() != 0 && prev_offset as usize != payload_byte_len {
+            return Err(InvalidFinalOffset);
+        }
+        Ok(())
+    }
+
+    /// Number of entries declared in the table header, or 0 if the header is
+    /// truncated.
+    fn read_num_nss(&self) -> u32 {
+        self.bytes
+            .get(..NUM_NSS_BYTE_LEN)
+            .map_or(0, |bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Maximum number of entries that could fit in the namespace table,
+    /// ignoring what's declared in the table header.
+    fn max_num_nss(&self) -> u32 {
+        (self.bytes.len().saturating_sub(NUM_NSS_BYTE_LEN) / (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN))
+            as u32
     }
 
     /// Read from namespace table given an index.
@@ -44,6 +117,10 @@ impl NsTable {
     ///
     /// Return a triple (id, start, end) which specifies the namespacd ID and
     /// its range [start, end) in the payload.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not below [`Self::len`].
     pub fn read_unchecked(&self, index: u32) -> (u32, u32, u32) {
         let pos = index as usize * (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN) + NUM_NSS_BYTE_LEN;
         let id = u32::from_le_bytes(self.bytes[pos..pos + NS_ID_BYTE_LEN].try_into().unwrap());
@@ -67,11 +144,19 @@ impl NsTable {
     /// Read from namespace table given a namespace ID.
     ///
     /// Return None if given ID is not present, or a tuple (start, end)
-    /// specifying its bytes range [start, end) in the payload.
+    /// specifying its bytes range [start, end) in the payload. If the ID
+    /// appears more than once, which a valid table doesn't allow, the first
+    /// entry wins.
     pub fn scan_for_id(&self, id: u32) -> Option<(u32, u32)> {
+        self.find_ns_id(id).map(|(_, start, end)| (start, end))
+    }
+
+    /// Same as [`Self::scan_for_id`], but also return the index of the entry,
+    /// as a triple (index, start, end).
+    pub fn find_ns_id(&self, id: u32) -> Option<(u32, u32, u32)> {
         let mut pos = NUM_NSS_BYTE_LEN;
         let mut last_offset = 0u32;
-        for _ in 0..self.len() {
+        for index in 0..self.len() {
             let cur_id =
                 u32::from_le_bytes(self.bytes[pos..pos + NS_ID_BYTE_LEN].try_into().unwrap());
             let cur_offset = u32::from_le_bytes(
@@ -80,7 +165,7 @@ impl NsTable {
                     .unwrap(),
             );
             if id == cur_id {
-                return Some((last_offset, cur_offset));
+                return Some((index, last_offset, cur_offset));
             }
             last_offset = cur_offset;
             pos += NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN;
@@ -114,3 +199,83 @@ impl Committable for NsTable {
         "NSTABLE".into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{NsTable, NsTableValidationError::*};
+
+    // Cases mirror the namespace table tests of espresso-sequencer.
+
+    #[test]
+    fn test_ns_table_byte_len() {
+        // Too short for a header
+        for bytes in [vec![], vec![0u8; 3]] {
+            assert_eq!(NsTable { bytes }.validate(0), Err(InvalidByteLen));
+        }
+        // Partial entry
+        let mut table = NsTable::mock_ns_table(&[(1, 10)]);
+        table.bytes.push(0);
+        assert_eq!(table.validate(10), Err(InvalidByteLen));
+        table.bytes.truncate(table.bytes.len() - 2);
+        assert_eq!(table.validate(10), Err(InvalidByteLen));
+        // Truncated tables still expose the entries that fit
+        assert_eq!(table.len(), 0);
+        assert_eq!(table.read(0), None);
+        assert_eq!(table.scan_for_id(1), None);
+    }
+
+    #[test]
+    fn test_ns_table_header() {
+        // Header declares more entries than there are
+        let mut table = NsTable::mock_ns_table(&[(1, 10), (2, 20)]);
+        table.bytes[..4].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(table.validate(20), Err(InvalidHeader));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.read(2), None);
+
+        // Header declares fewer entries than there are
+        table.bytes[..4].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(table.validate(20), Err(InvalidHeader));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.scan_for_id(2), None);
+    }
+
+    #[test]
+    fn test_ns_table_monotonic_increase() {
+        // Duplicate namespace IDs, the first one wins when scanning
+        let table = NsTable::mock_ns_table(&[(1, 10), (1, 20)]);
+        assert_eq!(table.validate(20), Err(NonIncreasingEntries));
+        assert_eq!(table.scan_for_id(1), Some((0, 10)));
+
+        // Decreasing namespace IDs
+        let table = NsTable::mock_ns_table(&[(2, 10), (1, 20)]);
+        assert_eq!(table.validate(20), Err(NonIncreasingEntries));
+
+        // Duplicate and decreasing offsets
+        for entries in [[(1, 10), (2, 10)], [(1, 20), (2, 10)]] {
+            let table = NsTable::mock_ns_table(&entries);
+            assert_eq!(table.validate(10), Err(NonIncreasingEntries));
+        }
+
+        // Empty first namespace
+        let table = NsTable::mock_ns_table(&[(1, 0), (2, 10)]);
+        assert_eq!(table.validate(10), Err(NonIncreasingEntries));
+    }
+
+    #[test]
+    fn test_ns_table_payload_byte_len() {
+        // An empty table is only valid for an empty payload
+        let table = NsTable::mock_ns_table(&[]);
+        assert_eq!(table.validate(0), Ok(()));
+        assert_eq!(table.validate(1), Err(ExpectNonemptyNsTable));
+
+        // The final offset must be the end of the payload
+        let table = NsTable::mock_ns_table(&[(1, 10), (2, 20)]);
+        assert_eq!(table.validate(20), Ok(()));
+        assert_eq!(table.validate(19), Err(InvalidFinalOffset));
+        assert_eq!(table.validate(21), Err(InvalidFinalOffset));
+        assert_eq!(table.read(1), Some((2, 10, 20)));
+        assert_eq!(table.scan_for_id(2), Some((10, 20)));
+        assert_eq!(table.find_ns_id(2), Some((1, 10, 20)));
+    }
+}
@@ -0,0 +1,766 @@
+//! Verification of namespace proofs against the payload commitment of a block,
+//! for each VID scheme used by Espresso, so that a batch can span blocks
+//! dispersed with ADVZ and, after the upgrade, with AvidM.
+//!
+//! AvidM is reimplemented here rather than imported from Espresso's `vid`
+//! crate, which requires a newer toolchain than SP1's (see the crate root).
+//! It follows the structure of the scheme: each namespace is Reed-Solomon
+//! encoded on its own and committed with a Merkle tree over the Keccak-256
+//! digests of the shares, and the block payload is committed with a Merkle tree
+//! over the namespace commitments. Its encoding details have not been checked
+//! against blocks produced by Espresso nodes yet, thus derivations reject AvidM
+//! blocks unless the `experimental-avidm` feature is enabled.
+
+use crate::{
+    block::payload::{
+        vid_scheme, AdvzCommitment, AdvzCommon, AdvzNsProof, NsProof, Vid, VidCommitment,
+        VidCommon, VidParam,
+    },
+    ns_table::NsTable,
+};
+use ark_bn254::{Fr, G1Affine, G1Projective};
+use ark_ec::VariableBaseMSM;
+use ark_ff::{One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use jf_merkle_tree::{hasher::HasherMerkleTree, MerkleCommitment, MerkleTreeScheme};
+use jf_utils::bytes_to_field;
+use jf_vid::{
+    payload_prover::{PayloadProver, Statement},
+    VidScheme,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use std::{collections::BTreeMap, ops::Range};
+use tagged_base64::tagged;
+
+/// VID schemes set up from the same public parameter, keyed by their number of
+/// storage nodes, so that blocks of a batch sharing it only pay the setup of
+/// ADVZ (e.g. its evaluation domains) once.
+///
+/// The re-commitments of ADVZ namespace proofs are deferred here, to be
+/// checked together by [`VidSchemes::verify_deferred`], unless the
+/// `advz-per-block` feature is enabled.
+pub struct VidSchemes<'a> {
+    vid_param: &'a VidParam,
+    advz: BTreeMap<u32, Vid>,
+    /// Deferred ADVZ claims, by number of evaluations of their polynomials
+    advz_claims: BTreeMap<usize, Vec<AdvzClaim>>,
+}
+
+impl<'a> VidSchemes<'a> {
+    pub fn new(vid_param: &'a VidParam) -> Self {
+        Self {
+            vid_param,
+            advz: BTreeMap::new(),
+            advz_claims: BTreeMap::new(),
+        }
+    }
+
+    /// Checks the ADVZ claims deferred so far, all at once for polynomials of
+    /// the same size: with a challenge `r` derived from all of them, the
+    /// commitment of `sum_i r^i p_i` must be `sum_i r^i C_i`. This takes one
+    /// MSM over the SRS instead of one per polynomial.
+    ///
+    /// On failure, the claims are checked one by one to return the lowest
+    /// height of a failing block.
+    pub fn verify_deferred(&mut self) -> Result<(), u64> {
+        let mut failed = None;
+        for (size, claims) in std::mem::take(&mut self.advz_claims) {
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-report-start: advz-batch");
+            let batch_ok = check_advz_claims(self.vid_param, size, &claims);
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-report-end: advz-batch");
+            if !batch_ok {
+                failed = claims
+                    .iter()
+                    .filter(|claim| {
+                        !check_advz_claims(self.vid_param, size, std::slice::from_ref(claim))
+                    })
+                    .map(|claim| claim.height)
+                    .chain(failed)
+                    .min()
+                    // A batch can't fail while each of its claims passes
+                    .or(claims.first().map(|claim| claim.height));
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// ADVZ with `num_storage_nodes`, set up on first use.
+    pub fn advz(&mut self, num_storage_nodes: u32) -> &Vid {
+        let vid_param = self.vid_param;
+        self.advz.entry(num_storage_nodes).or_insert_with(|| {
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-report-start: vid-setup");
+            let vid = vid_scheme(num_storage_nodes, vid_param);
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-report-end: vid-setup");
+            vid
+        })
+    }
+}
+
+/// Polynomials covering a namespace of an ADVZ payload, whose re-commitments
+/// are deferred to [`VidSchemes::verify_deferred`].
+struct AdvzClaim {
+    /// Height of the block, to report a failure
+    height: u64,
+    /// Evaluations of each polynomial over the ADVZ domain, padded with zeros
+    polys: Vec<Vec<Fr>>,
+    /// Commitments of these polynomials in the common data
+    commits: Vec<G1Affine>,
+}
+
+/// Whether the polynomials of `claims`, of `size` evaluations each, match
+/// their commitments, checked with a random linear combination.
+fn check_advz_claims(vid_param: &VidParam, size: usize, claims: &[AdvzClaim]) -> bool {
+    let (Some(domain), Some(powers)) = (
+        Radix2EvaluationDomain::<Fr>::new(size),
+        vid_param.srs.powers_of_g.get(..size),
+    ) else {
+        return false;
+    };
+    // Fiat-Shamir challenge, once every combined value is fixed
+    let mut transcript = Vec::new();
+    claims.iter().for_each(|claim| {
+        claim
+            .commits
+            .serialize_uncompressed(&mut transcript)
+            .unwrap();
+        claim.polys.serialize_uncompressed(&mut transcript).unwrap();
+    });
+    let r = Fr::from_le_bytes_mod_order(&Sha256::digest(&transcript));
+
+    let mut combined = vec![Fr::zero(); size];
+    let mut commits = vec![];
+    let mut scalars = vec![];
+    let mut power = Fr::one();
+    for claim in claims {
+        for (evals, commit) in claim.polys.iter().zip(&claim.commits) {
+            combined
+                .iter_mut()
+                .zip(evals)
+                .for_each(|(acc, eval)| *acc += power * eval);
+            commits.push(*commit);
+            scalars.push(power);
+            power *= r;
+        }
+    }
+    domain.ifft_in_place(&mut combined);
+    G1Projective::msm_unchecked(powers, &combined)
+        == G1Projective::msm_unchecked(&commits, &scalars)
+}
+
+/// Fields of ADVZ common data, private in jf-vid, read back from their
+/// canonical serialization.
+#[derive(CanonicalDeserialize)]
+struct AdvzCommonFields {
+    poly_commits: Vec<G1Affine>,
+    _all_evals_digest: [u8; 32],
+    payload_byte_len: u32,
+    num_storage_nodes: u32,
+    multiplicity: u32,
+}
+
+/// Fields of an ADVZ namespace proof, private in jf-vid, read back from their
+/// canonical serialization.
+#[derive(CanonicalDeserialize)]
+struct AdvzNsProofFields {
+    prefix_elems: Vec<Fr>,
+    suffix_elems: Vec<Fr>,
+    prefix_bytes: Vec<u8>,
+    suffix_bytes: Vec<u8>,
+}
+
+/// Read the fields of `value` back as `T`, which must span all its bytes.
+fn read_fields<T: CanonicalDeserialize>(value: &impl CanonicalSerialize) -> Option<T> {
+    let mut bytes = Vec::new();
+    value.serialize_uncompressed(&mut bytes).ok()?;
+    let mut reader = bytes.as_slice();
+    let fields = T::deserialize_uncompressed_unchecked(&mut reader).ok()?;
+    reader.is_empty().then_some(fields)
+}
+
+/// Rebuild the polynomials covering `ns_range` of an ADVZ payload from the
+/// namespace payload and the payload elements around it in `proof`, as
+/// jf-vid does, along with their commitments in `common`. Return their number
+/// of evaluations and the claim.
+///
+/// Return `None` if the statement is invalid or the proof isn't laid out as
+/// expected.
+fn advz_claim(
+    height: u64,
+    commit: &AdvzCommitment,
+    common: &AdvzCommon,
+    ns_range: &Range<usize>,
+    ns_payload: &[u8],
+    proof: &AdvzNsProof,
+) -> Option<(usize, AdvzClaim)> {
+    let fields: AdvzCommonFields = read_fields(common.as_ref())?;
+    let proof: AdvzNsProofFields = read_fields(proof.as_ref())?;
+    let payload_byte_len = fields.payload_byte_len as usize;
+    if payload_byte_len != <Vid as VidScheme>::get_payload_byte_len(common.as_ref()) as usize
+        || fields.num_storage_nodes != <Vid as VidScheme>::get_num_storage_nodes(common.as_ref())
+    {
+        return None;
+    }
+    <Vid as VidScheme>::is_consistent(commit.as_ref(), common.as_ref()).ok()?;
+    if ns_range.is_empty() || ns_range.end > payload_byte_len || ns_payload.len() != ns_range.len()
+    {
+        return None;
+    }
+
+    // Payload bytes are packed in field elements of `elem_bytes` each, and the
+    // elements in polynomials of `size` evaluations each, the recovery
+    // threshold of `vid_scheme` times the multiplicity
+    let elem_bytes = (Fr::MODULUS_BIT_SIZE as usize - 1) / 8;
+    let size = (1usize << fields.num_storage_nodes.checked_ilog2()?)
+        .checked_mul(fields.multiplicity as usize)?;
+    let range_elem = ns_range.start / elem_bytes..ns_range.end.div_ceil(elem_bytes);
+    let range_poly = range_elem.start / size..range_elem.end.div_ceil(size);
+    let payload_elems = payload_byte_len.div_ceil(elem_bytes);
+    // The proof must hold exactly the rest of the covering polynomials, so
+    // that the namespace sits at `ns_range` in them
+    if proof.prefix_bytes.len() != ns_range.start - range_elem.start * elem_bytes
+        || proof.suffix_bytes.len()
+            != (range_elem.end * elem_bytes).min(payload_byte_len) - ns_range.end
+        || proof.prefix_elems.len() != range_elem.start - range_poly.start * size
+        || proof.suffix_elems.len() != (range_poly.end * size).min(payload_elems) - range_elem.end
+    {
+        return None;
+    }
+
+    let commits = fields.poly_commits.get(range_poly)?.to_vec();
+    let elems: Vec<Fr> = proof
+        .prefix_elems
+        .into_iter()
+        .chain(bytes_to_field::<_, Fr>(
+            proof
+                .prefix_bytes
+                .iter()
+                .chain(ns_payload)
+                .chain(&proof.suffix_bytes),
+        ))
+        .chain(proof.suffix_elems)
+        .collect();
+    let polys: Vec<_> = elems
+        .chunks(size)
+        .map(|evals| {
+            let mut evals = evals.to_vec();
+            evals.resize(size, Fr::zero());
+            evals
+        })
+        .collect();
+    (polys.len() == commits.len()).then_some((
+        size,
+        AdvzClaim {
+            height,
+            polys,
+            commits,
+        },
+    ))
+}
+
+/// Verification of namespace proofs for a VID scheme.
+pub trait VidBackend {
+    type Commit;
+    type Common;
+    type NsProof;
+
+    /// Byte length of the whole block payload, which the namespace table is
+    /// validated against, as bound by `commit`.
+    ///
+    /// Return `None` if `common` doesn't match `commit`.
+    fn payload_byte_len(
+        commit: &Self::Commit,
+        common: &Self::Common,
+        ns_table: &NsTable,
+    ) -> Option<usize>;
+
+    /// Checks that `ns_payload` is the namespace at `ns_index` in the namespace
+    /// table, spanning `ns_range` of the block payload committed in `commit`.
+    ///
+    /// Part of the check may be deferred to `schemes`, reporting a failure at
+    /// `height`, see [`VidSchemes::verify_deferred`].
+    #[allow(clippy::too_many_arguments)]
+    fn verify_ns_proof(
+        schemes: &mut VidSchemes,
+        height: u64,
+        commit: &Self::Commit,
+        common: &Self::Common,
+        ns_index: u32,
+        ns_range: Range<usize>,
+        ns_payload: &[u8],
+        proof: &Self::NsProof,
+    ) -> bool;
+}
+
+/// ADVZ, whose namespace proofs are range proofs against the KZG commitments
+/// of the whole payload.
+///
+/// Namespaces are proven with `LargeRangeProof`, which carries the payload
+/// elements around the namespace so that the verifier re-commits each covered
+/// polynomial (an MSM over the SRS) and compares against `common`: there are
+/// no KZG openings, thus no pairing checks to batch across blocks.
+///
+/// Instead, the re-commitments of all the blocks of a batch are deferred and
+/// batched with a random linear combination, see [`VidSchemes`]. The fields of
+/// the proof and of the common data are private in jf-vid 0.4.5, thus read
+/// back from their canonical serialization, whose layout is pinned with the
+/// revision of jf-vid: a proof that isn't laid out as expected is rejected.
+/// With the `advz-per-block` feature, each proof is checked by jf-vid on its
+/// own instead.
+pub struct AdvzBackend;
+
+impl VidBackend for AdvzBackend {
+    type Commit = AdvzCommitment;
+    type Common = AdvzCommon;
+    type NsProof = AdvzNsProof;
+
+    /// The payload byte length is read from `common`, which the commitment
+    /// hashes.
+    fn payload_byte_len(
+        commit: &AdvzCommitment,
+        common: &AdvzCommon,
+        _ns_table: &NsTable,
+    ) -> Option<usize> {
+        <Vid as VidScheme>::is_consistent(commit.as_ref(), common.as_ref())
+            .ok()
+            .map(|_| <Vid as VidScheme>::get_payload_byte_len(common.as_ref()) as usize)
+    }
+
+    fn verify_ns_proof(
+        schemes: &mut VidSchemes,
+        height: u64,
+        commit: &AdvzCommitment,
+        common: &AdvzCommon,
+        _ns_index: u32,
+        ns_range: Range<usize>,
+        ns_payload: &[u8],
+        proof: &AdvzNsProof,
+    ) -> bool {
+        if !cfg!(feature = "advz-per-block") {
+            // A proof that isn't laid out as expected is rejected, rather than
+            // silently checked without batching
+            let Some((size, claim)) =
+                advz_claim(height, commit, common, &ns_range, ns_payload, proof)
+            else {
+                return false;
+            };
+            schemes.advz_claims.entry(size).or_default().push(claim);
+            return true;
+        }
+        let num_storage_nodes = <Vid as VidScheme>::get_num_storage_nodes(common.as_ref());
+        schemes
+            .advz(num_storage_nodes)
+            .payload_verify(
+                Statement {
+                    payload_subslice: ns_payload,
+                    range: ns_range,
+                    commit: commit.as_ref(),
+                    common: common.as_ref(),
+                },
+                proof.as_ref(),
+            )
+            .is_ok_and(|result| result.is_ok())
+    }
+}
+
+/// Merkle tree of AvidM, over share digests for a namespace commitment, and
+/// over namespace commitments for a payload commitment.
+pub type AvidMTree = HasherMerkleTree<Keccak256, [u8; 32]>;
+type AvidMNode = <AvidMTree as MerkleTreeScheme>::NodeValue;
+
+/// AvidM commitment, the root of the Merkle tree over the namespace
+/// commitments of the payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+#[tagged("AvidMCommit")]
+pub struct AvidMCommitment(pub AvidMNode);
+
+impl AsRef<AvidMNode> for AvidMCommitment {
+    fn as_ref(&self) -> &AvidMNode {
+        &self.0
+    }
+}
+
+/// Common data of AvidM
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvidMCommon {
+    /// Total weight of the storage nodes, i.e. the number of shares
+    pub total_weights: u32,
+    /// Number of shares needed to recover a namespace
+    pub recovery_threshold: u32,
+}
+
+/// Namespace proof of AvidM, a Merkle proof of the namespace commitment in the
+/// payload commitment. The namespace commitment is recomputed from the
+/// namespace payload by the verifier.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AvidMNsProof(pub <AvidMTree as MerkleTreeScheme>::MembershipProof);
+
+/// AvidM commitment of a namespace payload: encode it into `total_weights`
+/// shares, any `recovery_threshold` of which recover it, and commit to the
+/// Keccak-256 digests of the shares with a Merkle tree.
+///
+/// Return `None` if the parameters are invalid.
+pub fn avidm_ns_commit(common: &AvidMCommon, ns_payload: &[u8]) -> Option<[u8; 32]> {
+    let threshold = common.recovery_threshold as usize;
+    let total = common.total_weights as usize;
+    if threshold == 0 || threshold > total {
+        return None;
+    }
+    let domain = Radix2EvaluationDomain::<Fr>::new(total)?;
+
+    // 31 bytes per field element, with a `1` byte marking the end of the
+    // payload, padded to whole polynomials of `threshold` coefficients
+    let mut bytes = ns_payload.to_vec();
+    bytes.push(1);
+    let mut elems: Vec<_> = bytes.chunks(31).map(Fr::from_le_bytes_mod_order).collect();
+    elems.resize(elems.len().div_ceil(threshold) * threshold, Fr::zero());
+    let evals: Vec<_> = elems
+        .chunks(threshold)
+        .map(|coeffs| domain.fft(coeffs))
+        .collect();
+
+    // The i-th share holds the i-th evaluation of each polynomial
+    let digests = (0..total).map(|i| {
+        let mut hasher = Keccak256::new();
+        evals.iter().for_each(|poly_evals| {
+            let mut bytes = Vec::new();
+            poly_evals[i].serialize_compressed(&mut bytes).unwrap();
+            hasher.update(bytes);
+        });
+        <[u8; 32]>::from(hasher.finalize())
+    });
+    let tree = AvidMTree::from_elems(None, digests).ok()?;
+    Some(node_bytes(&tree.commitment().digest()))
+}
+
+/// AvidM Merkle tree over the commitments of each namespace of `payload`, as
+/// delimited by `ns_table`, whose root is the payload commitment.
+///
+/// Return `None` if the parameters or the namespace table are invalid.
+pub fn avidm_commit(common: &AvidMCommon, payload: &[u8], ns_table: &NsTable) -> Option<AvidMTree> {
+    ns_table.validate(payload.len()).ok()?;
+    let ns_commits = (0..ns_table.len())
+        .map(|index| {
+            let (_, start, end) = ns_table.read_unchecked(index);
+            avidm_ns_commit(common, &payload[start as usize..end as usize])
+        })
+        .collect::<Option<Vec<_>>>()?;
+    AvidMTree::from_elems(None, ns_commits).ok()
+}
+
+fn node_bytes(node: &AvidMNode) -> [u8; 32] {
+    let mut bytes = Vec::new();
+    node.serialize_compressed(&mut bytes).unwrap();
+    bytes.try_into().unwrap()
+}
+
+/// AvidM, whose namespace proofs are Merkle proofs of the namespace
+/// commitments.
+pub struct AvidMBackend;
+
+impl VidBackend for AvidMBackend {
+    type Commit = AvidMCommitment;
+    type Common = AvidMCommon;
+    type NsProof = AvidMNsProof;
+
+    /// The commitment covers exactly the namespaces of the table, so the
+    /// payload ends at the end of the last namespace.
+    fn payload_byte_len(
+        _commit: &AvidMCommitment,
+        _common: &AvidMCommon,
+        ns_table: &NsTable,
+    ) -> Option<usize> {
+        Some(
+            ns_table
+                .len()
+                .checked_sub(1)
+                .map_or(0, |index| ns_table.read_unchecked(index).2 as usize),
+        )
+    }
+
+    fn verify_ns_proof(
+        _schemes: &mut VidSchemes,
+        _height: u64,
+        commit: &AvidMCommitment,
+        common: &AvidMCommon,
+        ns_index: u32,
+        ns_range: Range<usize>,
+        ns_payload: &[u8],
+        proof: &AvidMNsProof,
+    ) -> bool {
+        ns_range.len() == ns_payload.len()
+            && proof.0.pos == ns_index as u64
+            && avidm_ns_commit(common, ns_payload)
+                .is_some_and(|ns_commit| proof.0.elem() == Some(&ns_commit))
+            && AvidMTree::verify(commit.0, proof.0.pos, &proof.0).is_ok_and(|result| result.is_ok())
+    }
+}
+
+/// Byte length of the whole block payload committed in `commit`, with the VID
+/// scheme of the commitment.
+///
+/// Return `None` if `common` doesn't match `commit`, including when they are of
+/// different schemes.
+pub fn payload_byte_len(
+    commit: &VidCommitment,
+    common: &VidCommon,
+    ns_table: &NsTable,
+) -> Option<usize> {
+    match (commit, common) {
+        (VidCommitment::V0(commit), VidCommon::V0(common)) => {
+            AdvzBackend::payload_byte_len(commit, common, ns_table)
+        },
+        (VidCommitment::V1(commit), VidCommon::V1(common)) => {
+            AvidMBackend::payload_byte_len(commit, common, ns_table)
+        },
+        _ => None,
+    }
+}
+
+/// Checks a namespace proof with the VID scheme of the payload commitment, for
+/// the block at `height`. The common data and the proof must be of the same
+/// scheme.
+///
+/// ADVZ re-commitments are deferred, see [`VidSchemes::verify_deferred`].
+#[allow(clippy::too_many_arguments)]
+pub fn verify_ns_proof(
+    schemes: &mut VidSchemes,
+    height: u64,
+    commit: &VidCommitment,
+    common: &VidCommon,
+    ns_index: u32,
+    ns_range: Range<usize>,
+    ns_payload: &[u8],
+    proof: &NsProof,
+) -> bool {
+    match (commit, common, proof) {
+        (VidCommitment::V0(commit), VidCommon::V0(common), NsProof::V0(proof)) => {
+            AdvzBackend::verify_ns_proof(
+                schemes, height, commit, common, ns_index, ns_range, ns_payload, proof,
+            )
+        },
+        (VidCommitment::V1(commit), VidCommon::V1(common), NsProof::V1(proof)) => {
+            AvidMBackend::verify_ns_proof(
+                schemes, height, commit, common, ns_index, ns_range, ns_payload, proof,
+            )
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        avidm_commit, payload_byte_len, verify_ns_proof, AvidMCommitment, AvidMCommon,
+        AvidMNsProof, VidSchemes,
+    };
+    use crate::{
+        block::payload::{
+            vid_scheme, AdvzCommitment, AdvzCommon, AdvzNsProof, NsProof, Vid, VidCommitment,
+            VidCommon, VidParam,
+        },
+        ns_table::NsTable,
+    };
+    use ark_bn254::Bn254;
+    use jf_merkle_tree::{MerkleCommitment, MerkleTreeScheme};
+    use jf_pcs::{prelude::UnivariateKzgPCS, PolynomialCommitmentScheme};
+    use jf_vid::{payload_prover::PayloadProver, VidScheme};
+
+    #[test]
+    fn test_avidm_ns_proof() {
+        let common = AvidMCommon {
+            total_weights: 10,
+            recovery_threshold: 4,
+        };
+        let payload: Vec<u8> = (0..100).collect();
+        let ns_table = NsTable::mock_ns_table(&[(1, 30), (2, 95), (3, 100)]);
+        let tree = avidm_commit(&common, &payload, &ns_table).unwrap();
+        let commit = VidCommitment::V1(AvidMCommitment(tree.commitment().digest()));
+        let proof = |index| {
+            let (_, proof) = tree.lookup(index).expect_ok().unwrap();
+            NsProof::V1(AvidMNsProof(proof))
+        };
+        // The SRS is only used by ADVZ
+        let vid_param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut ark_std::test_rng(), 2).unwrap(),
+        );
+        let mut schemes = VidSchemes::new(&vid_param);
+        let mut verify =
+            |index, range: std::ops::Range<usize>, ns_payload: &[u8], proof: &NsProof| {
+                verify_ns_proof(
+                    &mut schemes,
+                    0,
+                    &commit,
+                    &VidCommon::V1(common),
+                    index,
+                    range,
+                    ns_payload,
+                    proof,
+                )
+            };
+
+        assert!(verify(1, 30..95, &payload[30..95], &proof(1)));
+        assert!(verify(2, 95..100, &payload[95..], &proof(2)));
+
+        // Another namespace, or a modified one, is rejected
+        assert!(!verify(1, 30..95, &payload[30..95], &proof(0)));
+        assert!(!verify(0, 0..30, &payload[30..60], &proof(0)));
+        let mut ns_payload = payload[..30].to_vec();
+        ns_payload[7] ^= 1;
+        assert!(!verify(0, 0..30, &ns_payload, &proof(0)));
+        assert!(!verify(0, 0..29, &payload[..29], &proof(0)));
+
+        // The payload ends with its last namespace
+        let common = VidCommon::V1(common);
+        assert_eq!(payload_byte_len(&commit, &common, &ns_table), Some(100));
+        assert_eq!(
+            payload_byte_len(&commit, &common, &NsTable::mock_ns_table(&[])),
+            Some(0)
+        );
+
+        // Both encodings of the commitment keep its version
+        let json = serde_json::to_string(&commit).unwrap();
+        assert!(json.starts_with("\"AvidMCommit~"));
+        assert_eq!(
+            serde_json::from_str::<VidCommitment>(&json).unwrap(),
+            commit
+        );
+        assert_eq!(
+            bincode::deserialize::<VidCommitment>(&bincode::serialize(&commit).unwrap()).unwrap(),
+            commit
+        );
+    }
+
+    #[test]
+    fn test_advz_payload_byte_len() {
+        let vid_param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut ark_std::test_rng(), 8).unwrap(),
+        );
+        let mut vid = vid_scheme(10, &vid_param);
+        let ns_table = NsTable::mock_ns_table(&[(1, 100)]);
+        let disperse = |vid: &mut Vid, len| {
+            let disperse = vid.disperse(vec![7u8; len]).unwrap();
+            (
+                VidCommitment::V0(AdvzCommitment(disperse.commit)),
+                VidCommon::V0(AdvzCommon(disperse.common)),
+            )
+        };
+        let (commit, common) = disperse(&mut vid, 100);
+        let (other_commit, other_common) = disperse(&mut vid, 90);
+
+        assert_eq!(payload_byte_len(&commit, &common, &ns_table), Some(100));
+        assert_eq!(
+            payload_byte_len(&other_commit, &other_common, &ns_table),
+            Some(90)
+        );
+        // The length of another payload isn't trusted
+        assert_eq!(payload_byte_len(&commit, &other_common, &ns_table), None);
+        assert_eq!(payload_byte_len(&other_commit, &common, &ns_table), None);
+    }
+
+    #[test]
+    fn test_advz_batched_ns_proofs() {
+        let vid_param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut ark_std::test_rng(), 8).unwrap(),
+        );
+        let mut vid = vid_scheme(10, &vid_param);
+        // Namespaces spanning several polynomials, or within one
+        let payloads: Vec<Vec<u8>> = vec![
+            (0..1000).map(|i| i as u8).collect(),
+            (0..200).map(|i| (3 * i) as u8).collect(),
+        ];
+        let ns_ranges = [100..900, 40..50];
+        let blocks: Vec<_> = payloads
+            .iter()
+            .zip(&ns_ranges)
+            .map(|(payload, ns_range)| {
+                let disperse = vid.disperse(payload).unwrap();
+                (
+                    VidCommitment::V0(AdvzCommitment(disperse.commit)),
+                    VidCommon::V0(AdvzCommon(disperse.common)),
+                    NsProof::V0(AdvzNsProof(
+                        vid.payload_proof(payload, ns_range.clone()).unwrap(),
+                    )),
+                )
+            })
+            .collect();
+        let verify = |schemes: &mut VidSchemes, tampered: Option<usize>| {
+            blocks
+                .iter()
+                .zip(&payloads)
+                .zip(&ns_ranges)
+                .enumerate()
+                .all(|(height, (((commit, common, proof), payload), ns_range))| {
+                    let mut ns_payload = payload[ns_range.clone()].to_vec();
+                    if tampered == Some(height) {
+                        ns_payload[5] ^= 1;
+                    }
+                    verify_ns_proof(
+                        schemes,
+                        height as u64,
+                        commit,
+                        common,
+                        0,
+                        ns_range.clone(),
+                        &ns_payload,
+                        proof,
+                    )
+                })
+        };
+
+        let mut schemes = VidSchemes::new(&vid_param);
+        assert!(verify(&mut schemes, None));
+        // Unless checked block by block, the re-commitments are deferred
+        assert_eq!(
+            schemes.advz_claims.is_empty(),
+            cfg!(feature = "advz-per-block")
+        );
+        assert_eq!(schemes.verify_deferred(), Ok(()));
+
+        // A modified namespace fails at the height of its block
+        let mut schemes = VidSchemes::new(&vid_param);
+        if verify(&mut schemes, Some(1)) {
+            assert_eq!(schemes.verify_deferred(), Err(1));
+        } else {
+            assert!(cfg!(feature = "advz-per-block"));
+        }
+
+        // A proof for another range doesn't hold the payload elements around
+        // this one, and is rejected right away
+        let mut schemes = VidSchemes::new(&vid_param);
+        let (commit, common, proof) = &blocks[0];
+        assert!(!verify_ns_proof(
+            &mut schemes,
+            0,
+            commit,
+            common,
+            0,
+            200..300,
+            &payloads[0][200..300],
+            proof,
+        ));
+        assert!(schemes.advz_claims.is_empty());
+    }
+
+    #[test]
+    fn test_vid_schemes_cache() {
+        let vid_param = VidParam::new(
+            UnivariateKzgPCS::<Bn254>::gen_srs_for_testing(&mut ark_std::test_rng(), 16).unwrap(),
+        );
+        let mut schemes = VidSchemes::new(&vid_param);
+        for num_storage_nodes in [10, 10, 12, 10, 20] {
+            schemes.advz(num_storage_nodes);
+        }
+        // Set up once per distinct number of storage nodes
+        assert_eq!(
+            schemes.advz.keys().copied().collect::<Vec<_>>(),
+            vec![10, 12, 20]
+        );
+    }
+}